@@ -0,0 +1,279 @@
+use std::{
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::{Error, ErrorKind, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use mosquitto_rs::{Client, ConnectionStatus};
+
+use crate::{
+    cli_args::{ARGS, BrokerArgs},
+    utils::config_dir,
+};
+
+pub const DEFAULT_PORT: u16 = 1883;
+pub const DEFAULT_TLS_PORT: u16 = 8883;
+const DEFAULT_KEEPALIVE: u64 = 60;
+/// Certificates trusted when TLS is on without a CA file.
+const SYSTEM_CA_PATH: &str = "/etc/ssl/certs";
+
+/// How to reach and log into the MQTT broker. Empty strings stand for settings not used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrokerSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    /// A random id is picked when empty.
+    pub client_id: String,
+    /// Seconds between pings, mosquitto needs at least 5.
+    pub keepalive: u64,
+    pub tls: bool,
+    pub ca_file: String,
+    pub cert_file: String,
+    pub key_file: String,
+}
+
+impl Default for BrokerSettings {
+    fn default() -> Self {
+        BrokerSettings {
+            host: "localhost".to_owned(),
+            port: DEFAULT_PORT,
+            username: String::new(),
+            password: String::new(),
+            client_id: String::new(),
+            keepalive: DEFAULT_KEEPALIVE,
+            tls: false,
+            ca_file: String::new(),
+            cert_file: String::new(),
+            key_file: String::new(),
+        }
+    }
+}
+
+/// Key and value lines, as the config file stores them.
+impl Display for BrokerSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "host = {}", self.host)?;
+        writeln!(f, "port = {}", self.port)?;
+        writeln!(f, "username = {}", self.username)?;
+        writeln!(f, "password = {}", self.password)?;
+        writeln!(f, "client_id = {}", self.client_id)?;
+        writeln!(f, "keepalive = {}", self.keepalive)?;
+        writeln!(f, "tls = {}", self.tls)?;
+        writeln!(f, "ca_file = {}", self.ca_file)?;
+        writeln!(f, "cert_file = {}", self.cert_file)?;
+        writeln!(f, "key_file = {}", self.key_file)
+    }
+}
+
+impl BrokerSettings {
+    /// Settings of a config file with `key = value` lines. Missing keys keep their default,
+    /// empty lines and lines starting with `#` are skipped.
+    pub fn parse(content: &str) -> Result<BrokerSettings> {
+        let mut settings = BrokerSettings::default();
+        for line in content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(Error::other(format!("Expected key = value, got {}", line)))?;
+            settings.set(key.trim(), value.trim())?;
+        }
+        Ok(settings)
+    }
+
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let invalid = |e: &dyn Display| Error::other(format!("Invalid {} {}: {}", key, value, e));
+        match key {
+            "host" => self.host = value.to_owned(),
+            "port" => self.port = value.parse().map_err(|e| invalid(&e))?,
+            "username" => self.username = value.to_owned(),
+            "password" => self.password = value.to_owned(),
+            "client_id" => self.client_id = value.to_owned(),
+            "keepalive" => self.keepalive = value.parse().map_err(|e| invalid(&e))?,
+            "tls" => self.tls = value.parse().map_err(|e| invalid(&e))?,
+            "ca_file" => self.ca_file = value.to_owned(),
+            "cert_file" => self.cert_file = value.to_owned(),
+            "key_file" => self.key_file = value.to_owned(),
+            _ => return Err(Error::other(format!("Unknown broker setting {}", key)).into()),
+        }
+        Ok(())
+    }
+
+    /// Overrides the settings given on the command line. Turning TLS on moves the port to
+    /// 8883 unless a port was given too.
+    pub fn apply_args(mut self, broker_ip: Option<&str>, args: &BrokerArgs) -> BrokerSettings {
+        let text = |arg: &Option<String>, setting: &mut String| {
+            if let Some(arg) = arg {
+                *setting = arg.to_owned();
+            }
+        };
+        text(&broker_ip.map(|host| host.to_owned()), &mut self.host);
+        text(&args.username, &mut self.username);
+        text(&args.password, &mut self.password);
+        text(&args.client_id, &mut self.client_id);
+        text(&args.ca_file, &mut self.ca_file);
+        text(&args.cert_file, &mut self.cert_file);
+        text(&args.key_file, &mut self.key_file);
+        if args.tls && !self.tls {
+            self.tls = true;
+            if self.port == DEFAULT_PORT {
+                self.port = DEFAULT_TLS_PORT;
+            }
+        }
+        self.port = args.port.unwrap_or(self.port);
+        self.keepalive = args.keepalive.unwrap_or(self.keepalive);
+        self
+    }
+
+    /// Short form for the LOGS screen, e.g. `mqtts://user@broker:8883`. Leaves out the password.
+    pub fn describe(&self) -> String {
+        let scheme = if self.tls { "mqtts" } else { "mqtt" };
+        let user = match self.username.is_empty() {
+            true => "".to_owned(),
+            false => format!("{}@", self.username),
+        };
+        format!("{}://{}{}:{}", scheme, user, self.host, self.port)
+    }
+
+    /// Client with the id, credentials and TLS files set, not connected yet.
+    pub fn create_client(&self) -> Result<Client> {
+        let client = match self.client_id.is_empty() {
+            true => Client::with_auto_id()?,
+            false => Client::with_id(&self.client_id, true)?,
+        };
+        if !self.username.is_empty() {
+            client.set_username_and_password(Some(&self.username), non_empty(&self.password))?;
+        }
+        if self.tls {
+            let ca_path = self.ca_file.is_empty().then_some(SYSTEM_CA_PATH);
+            client.configure_tls(
+                non_empty(&self.ca_file),
+                ca_path,
+                non_empty(&self.cert_file),
+                non_empty(&self.key_file),
+                None,
+            )?;
+        }
+        Ok(client)
+    }
+
+    pub async fn connect(&self, client: &Client) -> Result<ConnectionStatus> {
+        Ok(client
+            .connect(
+                &self.host,
+                self.port.into(),
+                Duration::from_secs(self.keepalive),
+                None,
+            )
+            .await?)
+    }
+}
+
+fn non_empty(text: &str) -> Option<&str> {
+    (!text.is_empty()).then_some(text)
+}
+
+fn settings_path() -> PathBuf {
+    config_dir().join("broker.txt")
+}
+
+/// Saved settings with the command line arguments on top.
+pub fn load_broker_settings() -> Result<BrokerSettings> {
+    let saved = match fs::read_to_string(settings_path()) {
+        Ok(content) => BrokerSettings::parse(&content)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => BrokerSettings::default(),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(saved.apply_args(ARGS.broker_ip.as_deref(), &ARGS.broker))
+}
+
+/// Saves `settings` for the next start. Only the owner can read the file, as it holds the
+/// password.
+pub fn save_broker_settings(settings: &BrokerSettings) -> Result<()> {
+    write_private(&settings_path(), &settings.to_string())
+}
+
+/// Writes `content` to a file in the config dir only the owner can read.
+pub(crate) fn write_private(path: &Path, content: &str) -> Result<()> {
+    fs::create_dir_all(config_dir())?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // A file that already existed keeps its mode otherwise.
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_save_and_parse_settings() {
+        let settings = BrokerSettings {
+            host: "broker.example.com".to_owned(),
+            port: 8883,
+            username: "sensors".to_owned(),
+            password: "p4ss = word".to_owned(),
+            tls: true,
+            ca_file: "/etc/mqtt/ca.crt".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            BrokerSettings::parse(&settings.to_string()).unwrap(),
+            settings
+        );
+        assert_eq!(
+            BrokerSettings::parse("# saved\nport = 1884\n").unwrap(),
+            BrokerSettings {
+                port: 1884,
+                ..Default::default()
+            }
+        );
+        assert!(BrokerSettings::parse("port = many").is_err());
+        assert!(BrokerSettings::parse("colour = red").is_err());
+        assert_eq!(
+            settings.describe(),
+            "mqtts://sensors@broker.example.com:8883"
+        );
+    }
+
+    #[test]
+    fn should_override_saved_settings_with_args() {
+        let saved = BrokerSettings {
+            host: "saved".to_owned(),
+            username: "saved-user".to_owned(),
+            ..Default::default()
+        };
+
+        let args = BrokerArgs {
+            tls: true,
+            password: Some("secret".to_owned()),
+            ..Default::default()
+        };
+        let settings = saved.clone().apply_args(Some("cli"), &args);
+        assert_eq!(settings.host, "cli");
+        assert_eq!(settings.username, "saved-user");
+        assert_eq!(settings.password, "secret");
+        assert_eq!((settings.tls, settings.port), (true, DEFAULT_TLS_PORT));
+
+        let args = BrokerArgs {
+            tls: true,
+            port: Some(9001),
+            ..Default::default()
+        };
+        assert_eq!(saved.apply_args(None, &args).port, 9001);
+    }
+}
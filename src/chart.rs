@@ -0,0 +1,294 @@
+use anyhow::Result;
+use rusqlite::{Connection, params};
+
+use crate::{cli_args::ARGS, db_interactions::quote_identifier, time_range::TimeRange};
+
+/// Dots of a braille character, indexed by `[x][y]` within the 2x4 cell.
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+const BRAILLE_BLANK: u32 = 0x2800;
+/// Numeric values of topic `?1` from `?2` up to `?3`.
+const SERIES_CONDITIONS: &str = "topic = ?1 AND typeof(value) IN ('integer', 'real') \
+                                 AND timestamp >= ?2 AND timestamp < ?3";
+
+/// Numeric values of one topic, oldest first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Series {
+    pub points: Vec<(i64, f64)>,
+    /// First and last timestamp of the x axis.
+    pub from: i64,
+    pub to: i64,
+}
+
+impl Series {
+    /// Smallest and largest value, widened when they are equal so the line has room.
+    pub fn value_range(&self) -> (f64, f64) {
+        let (min, max) = self.points.iter().fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), (_, value)| (min.min(*value), max.max(*value)),
+        );
+        match (min.is_finite(), min == max) {
+            (false, _) => (0.0, 1.0),
+            (true, true) => (min - 1.0, max + 1.0),
+            (true, false) => (min, max),
+        }
+    }
+
+    /// Draws the values as a line of braille characters, `width` by `height` cells.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let dots_x = width * 2;
+        let dots_y = height * 4;
+        let mut cells = vec![vec![0u32; width]; height];
+        if dots_x == 0 || dots_y == 0 {
+            return vec![];
+        }
+
+        let (min, max) = self.value_range();
+        let span = (self.to - self.from).max(1) as f64;
+        let to_dot = |(timestamp, value): &(i64, f64)| -> (i64, i64) {
+            let x = ((*timestamp - self.from) as f64 / span * (dots_x - 1) as f64).round();
+            let y = ((max - *value) / (max - min) * (dots_y - 1) as f64).round();
+            (x as i64, y as i64)
+        };
+        let mut set_dot = |x: i64, y: i64| {
+            if (0..dots_x as i64).contains(&x) && (0..dots_y as i64).contains(&y) {
+                let (x, y) = (x as usize, y as usize);
+                cells[y / 4][x / 2] |= BRAILLE_DOTS[x % 2][y % 4];
+            }
+        };
+
+        let dots: Vec<(i64, i64)> = self.points.iter().map(to_dot).collect();
+        dots.iter().for_each(|(x, y)| set_dot(*x, *y));
+        dots.windows(2).for_each(|pair| {
+            draw_line(pair[0], pair[1], &mut set_dot);
+        });
+
+        cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|dots| char::from_u32(BRAILLE_BLANK + dots).unwrap_or(' '))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Bresenham's line between two dots, end points included.
+fn draw_line(from: (i64, i64), to: (i64, i64), set_dot: &mut impl FnMut(i64, i64)) {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let step_x = if x < to.0 { 1 } else { -1 };
+    let step_y = if y < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    loop {
+        set_dot(x, y);
+        if (x, y) == to {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Shortest span zooming in stops at, in seconds.
+const MIN_ZOOM_SPAN: i64 = 60;
+
+/// Scales `from..to` by `factor` around its middle, kept within `extent`, the first and last
+/// timestamp with a value, when there is one.
+pub fn zoom_range(from: i64, to: i64, factor: f64, extent: Option<(i64, i64)>) -> TimeRange {
+    let span = to.saturating_sub(from);
+    let middle = from.saturating_add(span / 2);
+    let half_span = ((span as f64 * factor) / 2.0).max(MIN_ZOOM_SPAN as f64 / 2.0) as i64;
+    let (mut from, mut to) = (
+        middle.saturating_sub(half_span),
+        middle.saturating_add(half_span),
+    );
+    if let Some((first, last)) = extent {
+        from = from.max(first);
+        to = to.min(last.saturating_add(1)).max(from.saturating_add(1));
+    }
+    TimeRange {
+        from: Some(from),
+        to: Some(to),
+    }
+}
+
+/// First and last timestamp with a numeric value of `topic`, none when there is none.
+pub fn get_extent(table_name: &str, topic: &str) -> Result<Option<(i64, i64)>> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_extent(&conn, table_name, topic, TimeRange::default())
+}
+
+fn query_extent(
+    conn: &Connection,
+    table_name: &str,
+    topic: &str,
+    range: TimeRange,
+) -> Result<Option<(i64, i64)>> {
+    let (first, last) = conn.query_row(
+        &format!(
+            "SELECT MIN(timestamp), MAX(timestamp) FROM {} WHERE {};",
+            quote_identifier(table_name),
+            SERIES_CONDITIONS
+        ),
+        params![
+            topic,
+            range.from.unwrap_or(i64::MIN),
+            range.to.unwrap_or(i64::MAX)
+        ],
+        |row| {
+            Ok((
+                row.get::<usize, Option<i64>>(0)?,
+                row.get::<usize, Option<i64>>(1)?,
+            ))
+        },
+    )?;
+    Ok(first.zip(last))
+}
+
+/// Reads the numeric values of `topic` in `range`, averaged down to at most `max_points`.
+/// Open bounds of the range end at the first and last value.
+pub fn get_series(
+    table_name: &str,
+    topic: &str,
+    range: TimeRange,
+    max_points: usize,
+) -> Result<Series> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_series(&conn, table_name, topic, range, max_points)
+}
+
+fn query_series(
+    conn: &Connection,
+    table_name: &str,
+    topic: &str,
+    range: TimeRange,
+    max_points: usize,
+) -> Result<Series> {
+    let table = quote_identifier(table_name);
+    let from = range.from.unwrap_or(i64::MIN);
+    let to = range.to.unwrap_or(i64::MAX);
+
+    let Some((first, last)) = query_extent(conn, table_name, topic, range)? else {
+        return Ok(Series {
+            points: vec![],
+            from: range.from.unwrap_or_default(),
+            to: range.to.unwrap_or_default(),
+        });
+    };
+
+    let bucket = ((last - first) / max_points.max(1) as i64).max(1);
+    let mut statement = conn.prepare(&format!(
+        "SELECT MIN(timestamp), AVG(value) FROM {} WHERE {} \
+         GROUP BY (timestamp - ?4) / ?5 ORDER BY 1;",
+        table, SERIES_CONDITIONS
+    ))?;
+    let points = statement
+        .query_map(params![topic, from, to, first, bucket], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<Vec<(i64, f64)>, rusqlite::Error>>()?;
+
+    Ok(Series {
+        points,
+        from: range.from.unwrap_or(first),
+        to: range.to.unwrap_or(last),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::migrations::{MIGRATIONS, migrate};
+
+    #[test]
+    fn should_render_line_with_braille() {
+        let series = Series {
+            points: vec![(0, 0.0), (10, 10.0)],
+            from: 0,
+            to: 10,
+        };
+
+        let lines = series.render(2, 1);
+        // Bottom left to top right, every column of dots has one dot.
+        assert_eq!(lines, vec!["⡠⠊".to_owned()]);
+        assert!(series.render(0, 0).is_empty());
+    }
+
+    #[test]
+    fn should_zoom_within_the_data() {
+        let mut range = zoom_range(1000, 2000, 2.0, None);
+        assert_eq!(
+            range,
+            TimeRange {
+                from: Some(500),
+                to: Some(2500),
+            }
+        );
+        for _ in 0..100 {
+            range = zoom_range(range.from.unwrap(), range.to.unwrap(), 2.0, None);
+        }
+        assert!(range.from.unwrap() < range.to.unwrap());
+
+        let range = zoom_range(i64::MIN, i64::MAX, 2.0, Some((1000, 2000)));
+        assert_eq!(
+            range,
+            TimeRange {
+                from: Some(1000),
+                to: Some(2001),
+            }
+        );
+        let range = zoom_range(1000, 1010, 0.5, None);
+        assert_eq!(range.to.unwrap() - range.from.unwrap(), MIN_ZOOM_SPAN);
+    }
+
+    #[test]
+    fn should_widen_flat_value_range() {
+        let series = Series {
+            points: vec![(0, 5.0), (1, 5.0)],
+            from: 0,
+            to: 1,
+        };
+        assert_eq!(series.value_range(), (4.0, 6.0));
+        assert_eq!(Series::default().value_range(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn should_read_and_average_series() {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        migrate(&mut conn, MIGRATIONS).expect("Should create tables");
+        conn.execute_batch(
+            "
+            INSERT INTO MEASUREMENTS VALUES
+                (0, '/temp', 1.0), (1, '/temp', 3.0), (10, '/temp', 5.0),
+                (11, '/temp', 'error'), (5, '/hum', 40.0);
+            ",
+        )
+        .expect("Should insert rows");
+
+        let series =
+            query_series(&conn, "MEASUREMENTS", "/temp", TimeRange::default(), 100).unwrap();
+        assert_eq!(series.points, vec![(0, 1.0), (1, 3.0), (10, 5.0)]);
+        assert_eq!((series.from, series.to), (0, 10));
+
+        let series = query_series(&conn, "MEASUREMENTS", "/temp", TimeRange::default(), 2).unwrap();
+        assert_eq!(series.points, vec![(0, 2.0), (10, 5.0)]);
+
+        let range = TimeRange {
+            from: Some(5),
+            to: Some(20),
+        };
+        let series = query_series(&conn, "MEASUREMENTS", "/temp", range, 100).unwrap();
+        assert_eq!(series.points, vec![(10, 5.0)]);
+        assert_eq!((series.from, series.to), (5, 20));
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::Error;
+
+use anyhow::Result;
+use chrono::Local;
+
+use crate::{
+    cli_args::{Command, FilterArgs, ServicesCommand},
+    db_interactions::{
+        DBRow, TableFilter, delete_rows_from_table, get_all_from_table, get_rows_at, get_tables,
+    },
+    export::export_table,
+    import::{ImportFormat, ImportOptions, parse_mapping, preview_import, run_import},
+    migrations::{get_pending_migrations, latest_version, run_migrations},
+    retention::{RetentionRule, apply_retention, load_rules, preview_retention},
+    time_range::{TimeRange, parse_time_bound},
+    tui_logs::print_logs,
+    utils::{ServiceKind, SystemDService},
+};
+
+/// Runs a subcommand without drawing the TUI. Output goes to stdout so it can be piped.
+pub fn run_command(command: &Command) -> Result<()> {
+    match command {
+        Command::Tui => {}
+        Command::Tables => {
+            get_tables()?
+                .iter()
+                .for_each(|table_name| println!("{}", table_name));
+        }
+        Command::Dump { table } => {
+            get_all_from_table(table)?
+                .iter()
+                .for_each(|row| println!("{}", row));
+        }
+        Command::Delete {
+            table,
+            timestamp,
+            row_topic,
+            rowid,
+            all,
+        } => {
+            let rows = get_rows_at(table, *timestamp, row_topic)?;
+            println!("{} rows matched", rows.len());
+            let rows: Vec<DBRow> = match rowid {
+                Some(rowid) => {
+                    let rows: Vec<DBRow> = rows
+                        .into_iter()
+                        .filter(|row| row.rowid == Some(*rowid))
+                        .collect();
+                    if rows.is_empty() {
+                        return Err(Error::other(format!(
+                            "No row with rowid {} at that timestamp and topic.",
+                            rowid
+                        ))
+                        .into());
+                    }
+                    rows
+                }
+                None if rows.len() > 1 && !all => {
+                    rows.iter().for_each(|row| match row.rowid {
+                        Some(rowid) => println!("rowid {}: {}", rowid, row),
+                        None => println!("{}", row),
+                    });
+                    return Err(Error::other(
+                        "Several rows match, pass --rowid to pick one or --all to delete them all.",
+                    )
+                    .into());
+                }
+                None => rows,
+            };
+            let deleted = delete_rows_from_table(&rows, table)?;
+            println!("{} rows deleted", deleted.len());
+        }
+        Command::Export {
+            table,
+            output,
+            format,
+            filter,
+        } => {
+            let row_count = export_table(table, &table_filter(filter)?, *format, output)?;
+            println!("{} rows exported to {}", row_count, output);
+        }
+        Command::Import {
+            table,
+            path,
+            format,
+            mapping,
+            dry_run,
+        } => {
+            let format = format
+                .or(ImportFormat::from_path(path))
+                .ok_or(Error::other(
+                    "Could not guess the file format, pass --format.",
+                ))?;
+            let options = ImportOptions {
+                path: path.to_owned(),
+                format,
+                table: *table,
+                mapping: parse_mapping(mapping)?,
+            };
+
+            let summary = match dry_run {
+                Some(row_count) => {
+                    let summary = preview_import(&options, *row_count)?;
+                    summary.preview.iter().for_each(|row| println!("{}", row));
+                    summary
+                }
+                None => run_import(&options)?,
+            };
+            println!("{}", summary.describe());
+        }
+        Command::Retention { rules, dry_run } => {
+            let rules = match rules.is_empty() {
+                true => load_rules()?,
+                false => rules
+                    .iter()
+                    .map(|rule| RetentionRule::parse(rule))
+                    .collect::<Result<Vec<RetentionRule>>>()?,
+            };
+            if rules.is_empty() {
+                println!("No retention rules configured");
+                return Ok(());
+            }
+
+            let effects = match dry_run {
+                true => preview_retention(&rules)?,
+                false => apply_retention(&rules)?,
+            };
+            effects
+                .iter()
+                .for_each(|effect| println!("{}", effect.describe()));
+        }
+        Command::Migrate { dry_run: true } => {
+            let (version, pending) = get_pending_migrations()?;
+            println!(
+                "Database at version {}, latest is {}",
+                version,
+                latest_version()
+            );
+            pending.iter().for_each(|migration| {
+                println!("\n{}: {}", migration.version, migration.description);
+                println!("{}", migration.sql.trim_end());
+            });
+        }
+        Command::Migrate { dry_run: false } => {
+            let applied = run_migrations()?;
+            if applied.is_empty() {
+                println!("Database already at version {}", latest_version());
+            }
+            applied.iter().for_each(|migration| {
+                println!("Applied {}: {}", migration.version, migration.description)
+            });
+        }
+        Command::Subscribe => print_logs()?,
+        Command::Services { command } => match command {
+            ServicesCommand::Status => print_services_status(),
+        },
+    }
+    Ok(())
+}
+
+fn table_filter(filter: &FilterArgs) -> Result<TableFilter> {
+    let now = Local::now();
+    let parse_bound = |bound: &Option<String>| match bound {
+        Some(bound) => parse_time_bound(bound, now),
+        None => Ok(None),
+    };
+
+    Ok(TableFilter {
+        text: filter.filter.to_owned(),
+        time_range: TimeRange {
+            from: parse_bound(&filter.from)?,
+            to: parse_bound(&filter.to)?,
+        },
+        topic: filter.topic.to_owned(),
+        sort: None,
+    })
+}
+
+fn print_services_status() {
+    [ServiceKind::SubStore, ServiceKind::DataDashboardServer]
+        .into_iter()
+        .for_each(|service_kind| {
+            let service = SystemDService::new(service_kind, vec![], None);
+            let status =
+                smol::block_on(service.check_unit_status()).unwrap_or_else(|e| e.to_string());
+            println!("{}: {}", service.service_kind.get_service_name(), status);
+        });
+}
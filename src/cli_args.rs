@@ -1,20 +1,161 @@
 use std::sync::LazyLock;
 
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand};
+
+use crate::{db_interactions::DataTable, export::ExportFormat, import::ImportFormat};
 
 /// TUI application to view project status.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
     /// Path to database
-    #[arg(short, long, default_value = "./data.db")]
+    #[arg(short, long, default_value = "./data.db", global = true)]
     pub db_path: String,
-    /// Initial host to connect to via mqtt
-    #[arg(short, long, default_value = "localhost")]
-    pub broker_ip: String,
+    /// Initial host to connect to via mqtt [default: the saved broker settings or localhost]
+    #[arg(short, long, global = true)]
+    pub broker_ip: Option<String>,
+    #[command(flatten)]
+    pub broker: BrokerArgs,
     /// Initial topic to subscribe to via mqtt
-    #[arg(short, long, default_value = "/#")]
+    #[arg(short, long, default_value = "/#", global = true)]
+    pub topic: String,
+    /// Run a single command without the TUI. Launches the TUI when omitted.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Launch the terminal UI (default)
+    Tui,
+    /// List the tables in the database
+    Tables,
+    /// Print every row of a table, newest last
+    Dump {
+        /// Name of the table to print
+        table: String,
+    },
+    /// Delete a row of a table with the given timestamp and topic
+    Delete {
+        /// Name of the table to delete from
+        table: String,
+        /// Unix timestamp of the row to delete
+        timestamp: u64,
+        /// Topic of the row to delete
+        #[arg(value_name = "TOPIC")]
+        row_topic: String,
+        /// Rowid of the row to delete when several rows have the timestamp and topic
+        #[arg(long, conflicts_with = "all")]
+        rowid: Option<i64>,
+        /// Delete every row with the timestamp and topic
+        #[arg(long)]
+        all: bool,
+    },
+    /// Write the rows of a table to a file
+    Export {
+        /// Name of the table to export
+        table: String,
+        /// File to write to
+        #[arg(short, long)]
+        output: String,
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Load a CSV or JSON Lines file into MEASUREMENTS or LOGS
+    Import {
+        #[arg(value_enum)]
+        table: DataTable,
+        /// File to read
+        path: String,
+        /// Format of the file, guessed from the extension when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<ImportFormat>,
+        /// Read a column from a differently named field, e.g. --map value=reading
+        #[arg(long = "map", value_name = "COLUMN=FIELD")]
+        mapping: Vec<String>,
+        /// Only parse and print the first N rows, nothing is written
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+        dry_run: Option<usize>,
+    },
+    /// Prune and average old rows with the retention rules from the CONFIGURE screen
+    Retention {
+        /// Use this rule instead of the saved ones, e.g. --rule "LOGS * 7d"
+        #[arg(long = "rule", value_name = "RULE")]
+        rules: Vec<String>,
+        /// Print what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Bring the database up to the latest schema, which every other command does first
+    Migrate {
+        /// Print the pending migrations without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Subscribe to the broker and print incoming messages until disconnected
+    Subscribe,
+    /// Inspect the systemd services managed from the CONFIGURE screen
+    Services {
+        #[command(subcommand)]
+        command: ServicesCommand,
+    },
+}
+
+/// Broker settings that replace the ones saved from the LOGS screen.
+#[derive(ClapArgs, Debug, Default)]
+pub struct BrokerArgs {
+    /// Port of the broker [default: 1883, or 8883 with --tls]
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+    /// User to log into the broker as
+    #[arg(long, global = true)]
+    pub username: Option<String>,
+    /// Password of the user
+    #[arg(long, global = true)]
+    pub password: Option<String>,
+    /// Client id to connect with [default: a random one]
+    #[arg(long, global = true)]
+    pub client_id: Option<String>,
+    /// Seconds between keepalive pings, at least 5 [default: 60]
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(5..))]
+    pub keepalive: Option<u64>,
+    /// Connect over TLS
+    #[arg(long, global = true)]
+    pub tls: bool,
+    /// CA certificate to verify the broker with [default: the system certificates]
+    #[arg(long, global = true)]
+    pub ca_file: Option<String>,
+    /// Client certificate, for brokers that require one
+    #[arg(long, global = true)]
+    pub cert_file: Option<String>,
+    /// Key of the client certificate
+    #[arg(long, global = true)]
+    pub key_file: Option<String>,
+}
+
+/// The same filter the FILTER dialog on the table screen applies.
+#[derive(ClapArgs, Debug)]
+pub struct FilterArgs {
+    /// Only rows containing this text in any column
+    #[arg(long, default_value = "")]
+    pub filter: String,
+    /// Only rows at or after this time, e.g. "2025-06-01 14:00" or "15m ago"
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Only rows before this time
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Only rows of topics matching this MQTT filter, e.g. "/home/+/temp" or "/home/#"
+    #[arg(long, default_value = "")]
     pub topic: String,
 }
 
-pub static ARGS: LazyLock<Args> = LazyLock::new(|| Args::parse());
+#[derive(Subcommand, Debug)]
+pub enum ServicesCommand {
+    /// Print the unit file state of each managed service
+    Status,
+}
+
+pub static ARGS: LazyLock<Args> = LazyLock::new(Args::parse);
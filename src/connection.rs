@@ -0,0 +1,132 @@
+use std::{fmt::Display, time::Duration};
+
+use mosquitto_rs::Error as MosquittoError;
+
+/// Wait before the first reconnect, doubled on each failed attempt.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// How long a connection has to stay up before the backoff starts over. Brokers that accept
+/// and drop right away, e.g. when another client takes over the id, keep backing off.
+const STABLE_CONNECTION: Duration = Duration::from_secs(30);
+/// CONNACK codes of bad credentials, for MQTT 3.1.1 and MQTT 5.
+const AUTH_FAILURE_CODES: [i32; 4] = [4, 5, 134, 135];
+
+/// Where the LOGS screen is with its broker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Retrying(Duration),
+    /// Not retried, as the broker would refuse the same credentials again.
+    AuthFailed,
+}
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Retrying(delay) => write!(f, "retrying in {}s", delay.as_secs()),
+            ConnectionState::AuthFailed => write!(f, "auth failed"),
+        }
+    }
+}
+
+/// State shown on the LOGS screen, with the drops since the broker was last picked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectionReport {
+    pub state: ConnectionState,
+    pub drops: u64,
+}
+
+impl ConnectionReport {
+    pub fn describe(&self) -> String {
+        match self.drops {
+            0 => self.state.to_string(),
+            1 => format!("{}, 1 drop", self.state),
+            drops => format!("{}, {} drops", self.state, drops),
+        }
+    }
+}
+
+/// Exponential backoff between reconnects, capped at a minute.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempts: u32,
+}
+
+impl Backoff {
+    /// Delay before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = FIRST_RETRY_DELAY
+            .saturating_mul(2u32.saturating_pow(self.attempts))
+            .min(MAX_RETRY_DELAY);
+        self.attempts = self.attempts.saturating_add(1);
+        delay
+    }
+
+    /// Starts over from the first delay if a connection held for `STABLE_CONNECTION`.
+    pub fn reset_if_held(&mut self, connected_for: Duration) {
+        if connected_for >= STABLE_CONNECTION {
+            self.attempts = 0;
+        }
+    }
+}
+
+/// Whether the broker refused the username or password, retrying will not help then.
+pub fn is_auth_failure(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<MosquittoError>(),
+        Some(MosquittoError::RejectedConnection(status)) if AUTH_FAILURE_CODES.contains(&status.0)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use mosquitto_rs::ConnectionStatus;
+
+    use super::*;
+
+    #[test]
+    fn should_double_delays_up_to_a_minute() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<u64> = (0..8).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+
+        (0..100).for_each(|_| {
+            backoff.next_delay();
+        });
+        assert_eq!(backoff.next_delay(), MAX_RETRY_DELAY);
+
+        backoff.reset_if_held(Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), MAX_RETRY_DELAY);
+        backoff.reset_if_held(STABLE_CONNECTION);
+        assert_eq!(backoff.next_delay(), FIRST_RETRY_DELAY);
+    }
+
+    #[test]
+    fn should_only_stop_retrying_on_bad_credentials() {
+        let rejected =
+            |code| anyhow::Error::from(MosquittoError::RejectedConnection(ConnectionStatus(code)));
+        assert!(is_auth_failure(&rejected(4)));
+        assert!(is_auth_failure(&rejected(5)));
+        assert!(!is_auth_failure(&rejected(3)));
+        assert!(!is_auth_failure(&anyhow::Error::from(
+            std::io::Error::other("Connection refused")
+        )));
+    }
+
+    #[test]
+    fn should_describe_state_with_drops() {
+        let report = |state, drops| ConnectionReport { state, drops }.describe();
+        assert_eq!(report(ConnectionState::Connected, 0), "connected");
+        assert_eq!(
+            report(ConnectionState::Retrying(Duration::from_secs(4)), 1),
+            "retrying in 4s, 1 drop"
+        );
+        assert_eq!(
+            report(ConnectionState::AuthFailed, 3),
+            "auth failed, 3 drops"
+        );
+    }
+}
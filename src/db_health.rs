@@ -0,0 +1,429 @@
+use std::{fs, io::Error};
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::{
+    cli_args::ARGS,
+    db_interactions::{query_table_columns, quote_identifier},
+    migrations::PROTECTED_INDEXES,
+};
+
+/// Table, view, index or trigger of the database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaObject {
+    pub kind: String,
+    pub name: String,
+    /// Table an index or trigger belongs to, the object itself otherwise.
+    pub table_name: String,
+    /// `CREATE` statement, missing for indexes SQLite creates on its own.
+    pub sql: Option<String>,
+    /// Only counted for tables.
+    pub row_count: Option<i64>,
+    /// Bytes of the pages the object takes, when SQLite has the `dbstat` table.
+    pub size: Option<i64>,
+}
+
+impl SchemaObject {
+    pub fn describe(&self) -> String {
+        let rows = self
+            .row_count
+            .map(|count| format!("{} rows", count))
+            .unwrap_or_default();
+        let size = self.size.map(format_size).unwrap_or_default();
+        format!(
+            "{:<8} {:<30} {:>14} {:>10}",
+            self.kind, self.name, rows, size
+        )
+    }
+}
+
+/// Storage and schema of the database file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DbHealth {
+    pub file_size: Option<u64>,
+    pub page_size: i64,
+    pub page_count: i64,
+    /// Pages left empty by deletes, given back to the file system by VACUUM.
+    pub freelist_count: i64,
+    pub journal_mode: String,
+    /// `ok`, or the problems `PRAGMA integrity_check` found.
+    pub integrity: Vec<String>,
+    pub objects: Vec<SchemaObject>,
+    pub has_dbstat: bool,
+}
+
+impl DbHealth {
+    pub fn describe(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "File size: {}",
+                self.file_size
+                    .map(|size| format_size(size as i64))
+                    .unwrap_or("unknown".to_owned())
+            ),
+            format!(
+                "Pages: {} of {}, {} free ({})",
+                self.page_count,
+                format_size(self.page_size),
+                self.freelist_count,
+                format_size(self.freelist_count * self.page_size)
+            ),
+            format!("Journal mode: {}", self.journal_mode),
+            format!("Integrity check: {}", self.integrity.join("\n  ")),
+        ];
+        if !self.has_dbstat {
+            lines.push("Sizes per table need SQLite built with dbstat.".to_owned());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Reads the schema, sizes and pragmas of `ARGS.db_path` and runs an integrity check.
+pub fn get_db_health() -> Result<DbHealth> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    let file_size = fs::metadata(&ARGS.db_path).map(|meta| meta.len()).ok();
+    query_db_health(&conn, file_size)
+}
+
+fn query_db_health(conn: &Connection, file_size: Option<u64>) -> Result<DbHealth> {
+    let mut statement = conn.prepare("PRAGMA integrity_check;")?;
+    let integrity = statement
+        .query_map([], |row| row.get::<usize, String>(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+
+    let sizes = query_object_sizes(conn);
+    let has_dbstat = sizes.is_ok();
+    let sizes = sizes.unwrap_or_default();
+
+    let mut statement = conn.prepare(
+        "SELECT type, name, tbl_name, sql FROM sqlite_master \
+         ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'view' THEN 1 WHEN 'index' THEN 2 ELSE 3 END, \
+         name;",
+    )?;
+    let objects = statement
+        .query_map([], |row| {
+            Ok(SchemaObject {
+                kind: row.get(0)?,
+                name: row.get(1)?,
+                table_name: row.get(2)?,
+                sql: row.get(3)?,
+                row_count: None,
+                size: None,
+            })
+        })?
+        .collect::<Result<Vec<SchemaObject>, rusqlite::Error>>()?
+        .into_iter()
+        .map(|mut object| -> Result<SchemaObject> {
+            if object.kind == "table" {
+                object.row_count = Some(conn.query_row(
+                    &format!("SELECT COUNT(*) FROM {};", quote_identifier(&object.name)),
+                    [],
+                    |row| row.get(0),
+                )?);
+            }
+            object.size = sizes
+                .iter()
+                .find(|(name, _)| *name == object.name)
+                .map(|(_, size)| *size);
+            Ok(object)
+        })
+        .collect::<Result<Vec<SchemaObject>>>()?;
+
+    Ok(DbHealth {
+        file_size,
+        page_size: read_pragma(conn, "page_size")?,
+        page_count: read_pragma(conn, "page_count")?,
+        freelist_count: read_pragma(conn, "freelist_count")?,
+        journal_mode: read_pragma(conn, "journal_mode")?,
+        integrity,
+        objects,
+        has_dbstat,
+    })
+}
+
+fn read_pragma<T: rusqlite::types::FromSql>(conn: &Connection, name: &str) -> Result<T> {
+    Ok(conn.query_row(&format!("PRAGMA {};", name), [], |row| row.get(0))?)
+}
+
+/// Bytes per table and index, fails when SQLite was built without `dbstat`.
+fn query_object_sizes(conn: &Connection) -> Result<Vec<(String, i64)>> {
+    let mut statement = conn.prepare("SELECT name, SUM(pgsize) FROM dbstat GROUP BY name;")?;
+    let sizes = statement
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, i64)>, rusqlite::Error>>()?;
+    Ok(sizes)
+}
+
+/// Bytes in the largest unit that keeps the number above one.
+pub fn format_size(bytes: i64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < units.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, units[unit]),
+    }
+}
+
+/// Upkeep commands offered on the database screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Maintenance {
+    Vacuum,
+    Analyze,
+    WalCheckpoint,
+}
+
+impl Maintenance {
+    pub const ALL: [Maintenance; 3] = [
+        Maintenance::Vacuum,
+        Maintenance::Analyze,
+        Maintenance::WalCheckpoint,
+    ];
+
+    pub fn get_title(&self) -> &str {
+        match self {
+            Maintenance::Vacuum => "VACUUM",
+            Maintenance::Analyze => "ANALYZE",
+            Maintenance::WalCheckpoint => "CHECKPOINT",
+        }
+    }
+
+    pub fn get_description(&self) -> &str {
+        match self {
+            Maintenance::Vacuum => {
+                "Rewrites the whole file without its free pages. Needs as much free disk space as \
+                 the file takes and blocks writers until done."
+            }
+            Maintenance::Analyze => "Gathers statistics the query planner uses to pick indexes.",
+            Maintenance::WalCheckpoint => {
+                "Copies the write-ahead log into the database file and truncates the log."
+            }
+        }
+    }
+}
+
+/// Runs `maintenance` on `ARGS.db_path` and describes the outcome.
+pub fn run_maintenance(maintenance: Maintenance) -> Result<String> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    let file_size = || {
+        fs::metadata(&ARGS.db_path)
+            .map(|meta| meta.len() as i64)
+            .ok()
+    };
+
+    let before = file_size();
+    let result = run(&conn, maintenance)?;
+    match (maintenance, before, file_size()) {
+        (Maintenance::Vacuum, Some(before), Some(after)) => Ok(format!(
+            "{} File went from {} to {}.",
+            result,
+            format_size(before),
+            format_size(after)
+        )),
+        _ => Ok(result),
+    }
+}
+
+fn run(conn: &Connection, maintenance: Maintenance) -> Result<String> {
+    match maintenance {
+        Maintenance::Vacuum => {
+            conn.execute_batch("VACUUM;")?;
+            Ok("Vacuumed.".to_owned())
+        }
+        Maintenance::Analyze => {
+            conn.execute_batch("ANALYZE;")?;
+            Ok("Analyzed.".to_owned())
+        }
+        Maintenance::WalCheckpoint => {
+            let (busy, log, checkpointed) =
+                conn.query_row("PRAGMA wal_checkpoint(TRUNCATE);", [], |row| {
+                    Ok((
+                        row.get::<usize, i64>(0)?,
+                        row.get::<usize, i64>(1)?,
+                        row.get::<usize, i64>(2)?,
+                    ))
+                })?;
+            Ok(match (busy, log) {
+                (_, -1) => "The database is not in WAL mode, nothing to checkpoint.".to_owned(),
+                (0, _) => format!("Checkpointed {} of {} pages.", checkpointed, log),
+                _ => format!(
+                    "Busy, only checkpointed {} of {} pages. Try again later.",
+                    checkpointed, log
+                ),
+            })
+        }
+    }
+}
+
+/// Creates the index `name` on `columns` of `table_name`.
+pub fn create_index(name: &str, table_name: &str, columns: &[String]) -> Result<()> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    add_index(&conn, name, table_name, columns)
+}
+
+fn add_index(conn: &Connection, name: &str, table_name: &str, columns: &[String]) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(Error::other("The index needs a name.").into());
+    }
+    if columns.is_empty() {
+        return Err(Error::other("The index needs at least one column.").into());
+    }
+    let table_columns = query_table_columns(conn, table_name)?;
+    if let Some(missing) = columns.iter().find(|column| {
+        !table_columns
+            .iter()
+            .any(|existing| existing.name == **column)
+    }) {
+        return Err(Error::other(format!("This table has no {} column.", missing)).into());
+    }
+
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(column))
+        .collect();
+    conn.execute(
+        &format!(
+            "CREATE INDEX {} ON {} ({});",
+            quote_identifier(name.trim()),
+            quote_identifier(table_name),
+            columns.join(", ")
+        ),
+        (),
+    )?;
+    Ok(())
+}
+
+/// Drops the index `name`. The indexes the migrations create can not be dropped, nothing
+/// would create them again.
+pub fn drop_index(name: &str) -> Result<()> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    remove_index(&conn, name)
+}
+
+fn remove_index(conn: &Connection, name: &str) -> Result<()> {
+    let is_managed = PROTECTED_INDEXES
+        .iter()
+        .any(|managed| managed.eq_ignore_ascii_case(name));
+    if is_managed {
+        return Err(Error::other(format!(
+            "{} speeds up the table screens and would not be created again.",
+            name
+        ))
+        .into());
+    }
+    conn.execute(&format!("DROP INDEX {};", quote_identifier(name)), ())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::migrations::{MIGRATIONS, migrate};
+
+    fn setup_test_db() -> Connection {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        migrate(&mut conn, MIGRATIONS).expect("Should create tables");
+        conn.execute_batch(
+            "
+            INSERT INTO MEASUREMENTS VALUES (1, '/temp', 20.0), (2, '/temp', 21.0);
+            CREATE INDEX measurements_topic ON MEASUREMENTS (topic);
+            CREATE VIEW TEMPS AS SELECT * FROM MEASUREMENTS WHERE topic = '/temp';
+            CREATE TRIGGER no_empty_topic BEFORE INSERT ON MEASUREMENTS
+                WHEN NEW.topic = '' BEGIN SELECT RAISE(ABORT, 'empty topic'); END;
+            ",
+        )
+        .expect("Should create schema");
+        conn
+    }
+
+    #[test]
+    fn should_list_schema_with_counts() {
+        let conn = setup_test_db();
+        let health = query_db_health(&conn, None).unwrap();
+
+        let kinds: Vec<(&str, &str)> = health
+            .objects
+            .iter()
+            .map(|object| (object.kind.as_str(), object.name.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("table", "LOGS"),
+                ("table", "MEASUREMENTS"),
+                ("view", "TEMPS"),
+                ("index", "LOGS_TIMESTAMP"),
+                ("index", "LOGS_TOPIC_TIMESTAMP"),
+                ("index", "MEASUREMENTS_TIMESTAMP"),
+                ("index", "MEASUREMENTS_TOPIC_TIMESTAMP"),
+                ("index", "measurements_topic"),
+                ("trigger", "no_empty_topic"),
+            ]
+        );
+        assert_eq!(health.objects[0].row_count, Some(0));
+        assert_eq!(health.objects[1].row_count, Some(2));
+        assert_eq!(health.objects[2].row_count, None);
+        assert_eq!(health.objects[7].table_name, "MEASUREMENTS");
+        assert!(
+            health.objects[8]
+                .sql
+                .as_ref()
+                .is_some_and(|sql| sql.starts_with("CREATE TRIGGER"))
+        );
+        assert_eq!(health.integrity, vec!["ok"]);
+        assert!(health.page_size > 0);
+        if health.has_dbstat {
+            assert!(health.objects[1].size.is_some_and(|size| size > 0));
+        }
+    }
+
+    #[test]
+    fn should_run_maintenance() {
+        let conn = setup_test_db();
+        assert_eq!(run(&conn, Maintenance::Analyze).unwrap(), "Analyzed.");
+        assert_eq!(run(&conn, Maintenance::Vacuum).unwrap(), "Vacuumed.");
+        assert!(
+            run(&conn, Maintenance::WalCheckpoint)
+                .unwrap()
+                .contains("not in WAL mode")
+        );
+    }
+
+    #[test]
+    fn should_create_and_drop_custom_indexes() {
+        let conn = setup_test_db();
+        let index_names = || -> Vec<String> {
+            query_db_health(&conn, None)
+                .unwrap()
+                .objects
+                .into_iter()
+                .filter(|object| {
+                    object.kind == "index" && !PROTECTED_INDEXES.contains(&object.name.as_str())
+                })
+                .map(|object| object.name)
+                .collect()
+        };
+
+        add_index(&conn, "by value", "MEASUREMENTS", &["value".to_owned()]).unwrap();
+        assert_eq!(index_names(), vec!["by value", "measurements_topic"]);
+        assert!(add_index(&conn, "by unit", "MEASUREMENTS", &["unit".to_owned()]).is_err());
+        assert!(add_index(&conn, "", "MEASUREMENTS", &["value".to_owned()]).is_err());
+
+        remove_index(&conn, "by value").unwrap();
+        assert_eq!(index_names(), vec!["measurements_topic"]);
+        assert!(remove_index(&conn, "MEASUREMENTS_TIMESTAMP").is_err());
+        assert!(remove_index(&conn, "measurements_timestamp").is_err());
+    }
+
+    #[test]
+    fn should_format_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(2 * 1024 * 1024 * 1024), "2.0 GiB");
+    }
+}
@@ -1,151 +1,1128 @@
-use std::{
-    fmt::Display,
-    io::{Error, ErrorKind},
-    time::SystemTime,
-};
+use std::{fmt::Display, io::Error, sync::Arc, time::SystemTime};
 
 use anyhow::Result;
-use chrono::DateTime;
-use rusqlite::{Connection, params, types::FromSql};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use rusqlite::{
+    Connection, params, params_from_iter,
+    types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef},
+};
 
-use crate::cli_args::ARGS;
+use crate::{
+    cli_args::ARGS,
+    migrations::run_migrations,
+    subscriptions::validate_filter,
+    time_range::{TimeRange, parse_time_bound},
+};
 
-pub struct DBRow {
-    timestamp: u64,
-    pub topic: String,
-    pub value: String,
+/// Rows of a query console result that are read, so big results do not fill the memory.
+pub const QUERY_ROW_LIMIT: usize = 1000;
+
+/// Rows a delete of matching rows keeps in memory for UNDO. Bigger deletes can not be undone,
+/// as a copy of every row would not fit on small hosts.
+pub const UNDO_ROW_LIMIT: usize = 10_000;
+
+/// A column of a table or view, as reported by `PRAGMA table_info`.
+#[derive(Clone, Debug, Default)]
+pub struct TableColumn {
+    pub name: String,
+    /// Declared type, empty for untyped columns and most view columns.
+    pub decl_type: String,
+    /// Part of the declared primary key.
+    pub primary_key: bool,
 }
 
-impl From<&DBRow> for String {
-    fn from(value: &DBRow) -> Self {
-        let limited_db_row = value.fix_col_lengths(20);
-        let timestamp =
-            DateTime::from_timestamp(value.timestamp as i64, 0).unwrap_or(SystemTime::now().into());
+/// Type affinity SQLite derives from a declared column type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Affinity {
+    INTEGER,
+    REAL,
+    NUMERIC,
+    TEXT,
+    BLOB,
+}
 
-        format!(
-            "| {} | {} | {}",
-            timestamp.naive_local().to_string(),
-            limited_db_row.topic,
-            limited_db_row.value,
-        )
+impl TableColumn {
+    /// Follows the rules of section 3.1 of https://www.sqlite.org/datatype3.html.
+    pub fn get_affinity(&self) -> Affinity {
+        let decl_type = self.decl_type.to_uppercase();
+        if decl_type.contains("INT") {
+            Affinity::INTEGER
+        } else if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|name| decl_type.contains(name))
+        {
+            Affinity::TEXT
+        } else if decl_type.contains("BLOB") || decl_type.is_empty() {
+            Affinity::BLOB
+        } else if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|name| decl_type.contains(name))
+        {
+            Affinity::REAL
+        } else {
+            Affinity::NUMERIC
+        }
     }
+
+    /// Converts text typed for this column into a value of its affinity. Empty text is NULL
+    /// for anything but TEXT columns. `timestamp` columns also take anything the time filter
+    /// accepts.
+    pub fn parse_value(&self, raw: &str) -> Result<ColumnKind> {
+        let affinity = self.get_affinity();
+        let trimmed = raw.trim();
+
+        if trimmed.is_empty() && affinity != Affinity::TEXT {
+            return Ok(ColumnKind::NULL);
+        }
+
+        let invalid = |expected: &str| -> anyhow::Error {
+            Error::other(format!("{} is not {}: {}", self.name, expected, trimmed)).into()
+        };
+
+        match affinity {
+            // Fractional seconds are dropped, the tables store whole seconds.
+            Affinity::INTEGER if is_timestamp_column(self) => match trimmed.parse::<f64>() {
+                Ok(seconds) if trimmed.contains('.') => Ok(ColumnKind::INTEGER(seconds as i64)),
+                _ => parse_time_bound(trimmed, Local::now())?
+                    .map(ColumnKind::INTEGER)
+                    .ok_or(invalid("a time")),
+            },
+            Affinity::INTEGER => trimmed
+                .parse::<i64>()
+                .map(ColumnKind::INTEGER)
+                .map_err(|_| invalid("an integer")),
+            Affinity::REAL => trimmed
+                .parse::<f64>()
+                .map(ColumnKind::FLOAT)
+                .map_err(|_| invalid("a number")),
+            Affinity::NUMERIC => trimmed
+                .parse::<i64>()
+                .map(ColumnKind::INTEGER)
+                .or(trimmed.parse::<f64>().map(ColumnKind::FLOAT))
+                .map_err(|_| invalid("a number")),
+            Affinity::TEXT | Affinity::BLOB => Ok(ColumnKind::STRING(raw.to_owned())),
+        }
+    }
+}
+
+/// A row of any table. Values are in the same order as `columns`, which is shared by every
+/// row read from the same table.
+#[derive(Clone)]
+pub struct DBRow {
+    pub columns: Arc<Vec<TableColumn>>,
+    pub values: Vec<ColumnKind>,
+    /// Identifies the row for updates and deletes. Missing for views, `WITHOUT ROWID` tables
+    /// and query results.
+    pub rowid: Option<i64>,
 }
 
-impl Clone for DBRow {
-    fn clone(&self) -> Self {
-        DBRow {
-            timestamp: self.timestamp,
-            value: self.value.to_owned(),
-            topic: self.topic.to_owned(),
+impl DBRow {
+    pub fn get(&self, column_name: &str) -> Option<&ColumnKind> {
+        self.columns
+            .iter()
+            .position(|column| column.name == column_name)
+            .and_then(|idx| self.values.get(idx))
+    }
+
+    /// The value of a column formatted for display. Integer `timestamp` columns are shown as
+    /// a date.
+    pub fn display_value(&self, idx: usize) -> String {
+        let is_timestamp = self.columns.get(idx).is_some_and(is_timestamp_column);
+
+        match self.values.get(idx) {
+            Some(ColumnKind::INTEGER(val)) if is_timestamp => {
+                let timestamp =
+                    DateTime::from_timestamp(*val, 0).unwrap_or(SystemTime::now().into());
+                timestamp.naive_local().to_string()
+            }
+            Some(val) => val.to_string(),
+            None => "".to_owned(),
         }
     }
+
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        (0..self.values.len()).any(|idx| self.display_value(idx).contains(filter))
+    }
+
+    pub fn display_values(&self) -> Vec<String> {
+        (0..self.values.len())
+            .map(|idx| self.display_value(idx))
+            .collect()
+    }
 }
 
 impl Display for DBRow {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!(
-            "{} -> {} -> {}",
-            self.timestamp, self.value, self.topic
-        ))
+        let values: Vec<String> = self.values.iter().map(|val| val.to_string()).collect();
+        f.write_str(&values.join(" -> "))
     }
 }
 
-enum ColumnKind {
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnKind {
+    NULL,
+    INTEGER(i64),
     FLOAT(f64),
     STRING(String),
+    BLOB(Vec<u8>),
 }
 
 impl Display for ColumnKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            ColumnKind::NULL => f.write_str("NULL"),
+            ColumnKind::INTEGER(val) => f.write_str(&val.to_string()),
             ColumnKind::FLOAT(val) => f.write_str(&format!("{:.2}", val)),
-            ColumnKind::STRING(val) => f.write_str(&format!("{}", val)),
+            ColumnKind::STRING(val) => f.write_str(val),
+            ColumnKind::BLOB(val) => f.write_str(&format!("<{} bytes>", val.len())),
         }
     }
 }
 
-impl FromSql for ColumnKind {
-    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
-        if let Ok(val) = value.as_f64() {
-            Ok(ColumnKind::FLOAT(val))
-        } else {
-            let bytes = value.as_bytes().unwrap_or(&[]);
-            let val = String::from_utf8(bytes.into());
-            Ok(ColumnKind::STRING(val.unwrap_or("No value".to_owned())))
+impl ColumnKind {
+    /// The value as text that `TableColumn::parse_value` reads back unchanged, used to fill
+    /// edit forms. Unlike `Display` floats are not rounded.
+    pub fn to_raw_string(&self) -> String {
+        match self {
+            ColumnKind::NULL => "".to_owned(),
+            ColumnKind::INTEGER(val) => val.to_string(),
+            ColumnKind::FLOAT(val) => val.to_string(),
+            ColumnKind::STRING(val) => val.to_owned(),
+            ColumnKind::BLOB(val) => format!("<{} bytes>", val.len()),
         }
     }
 }
 
+impl FromSql for ColumnKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(match value {
+            ValueRef::Null => ColumnKind::NULL,
+            ValueRef::Integer(val) => ColumnKind::INTEGER(val),
+            ValueRef::Real(val) => ColumnKind::FLOAT(val),
+            ValueRef::Text(bytes) => {
+                ColumnKind::STRING(String::from_utf8(bytes.into()).unwrap_or("No value".to_owned()))
+            }
+            ValueRef::Blob(bytes) => ColumnKind::BLOB(bytes.into()),
+        })
+    }
+}
+
+impl ToSql for ColumnKind {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            ColumnKind::NULL => ValueRef::Null,
+            ColumnKind::INTEGER(val) => ValueRef::Integer(*val),
+            ColumnKind::FLOAT(val) => ValueRef::Real(*val),
+            ColumnKind::STRING(val) => ValueRef::Text(val.as_bytes()),
+            ColumnKind::BLOB(val) => ValueRef::Blob(val),
+        }))
+    }
+}
+
+/// Quotes a table or column name so it can be used in a query.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The `WHERE` clause matching `row`, with placeholders numbered from `?{first_param}`. Uses
+/// the rowid when the row has one, then the primary key, then every column.
+fn row_condition(row: &DBRow, first_param: usize) -> (String, Vec<ColumnKind>) {
+    if let Some(rowid) = row.rowid
+        && let Some(alias) = rowid_alias(&row.columns)
+    {
+        return (
+            format!("WHERE {} = ?{}", alias, first_param),
+            vec![ColumnKind::INTEGER(rowid)],
+        );
+    }
+
+    let has_primary_key = row.columns.iter().any(|column| column.primary_key);
+    let (conditions, params): (Vec<String>, Vec<ColumnKind>) = row
+        .columns
+        .iter()
+        .zip(row.values.iter())
+        .filter(|(column, _)| column.primary_key || !has_primary_key)
+        .enumerate()
+        .map(|(idx, (column, value))| {
+            (
+                format!(
+                    "{} IS ?{}",
+                    quote_identifier(&column.name),
+                    first_param + idx
+                ),
+                value.clone(),
+            )
+        })
+        .unzip();
+
+    (format!("WHERE {}", conditions.join(" AND ")), params)
+}
+
+/// Number of rows of `table_name` an update or delete of `row` would change.
+pub fn count_rows_matching(row: &DBRow, table_name: &str) -> Result<usize> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    count_matching(&conn, row, table_name)
+}
+
+fn count_matching(conn: &Connection, row: &DBRow, table_name: &str) -> Result<usize> {
+    let (where_clause, params) = row_condition(row, 1);
+    let query = format!(
+        "SELECT COUNT(*) FROM {} {};",
+        quote_identifier(table_name),
+        where_clause
+    );
+
+    let count = conn.query_row(&query, params_from_iter(params.iter()), |row| {
+        row.get::<usize, i64>(0)
+    })?;
+
+    Ok(count as usize)
+}
+
+/// Deletes `row` from `table_name`.
 pub fn delete_row_from_table(row: &DBRow, table_name: &str) -> Result<usize> {
     let conn = Connection::open(&ARGS.db_path)?;
+    delete_row(&conn, row, table_name)
+}
+
+fn delete_row(conn: &Connection, row: &DBRow, table_name: &str) -> Result<usize> {
+    let (where_clause, params) = row_condition(row, 1);
     let query = format!(
-        "DELETE FROM {} WHERE timestamp = ?1 and topic = ?2;",
-        table_name
+        "DELETE FROM {} {};",
+        quote_identifier(table_name),
+        where_clause
     );
 
-    let rows_changed = conn.execute(&query, params![row.timestamp, row.topic])?;
+    let rows_changed = conn.execute(&query, params_from_iter(params.iter()))?;
 
     Ok(rows_changed)
 }
 
-pub fn get_all_from_table(table_name: &str) -> Result<Vec<DBRow>> {
+/// Deletes `rows` from `table_name` in a single transaction. Returns the rows that were
+/// deleted, once for every row removed, so they can be restored.
+pub fn delete_rows_from_table(rows: &[DBRow], table_name: &str) -> Result<Vec<DBRow>> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    delete_rows(&mut conn, rows, table_name)
+}
+
+fn delete_rows(conn: &mut Connection, rows: &[DBRow], table_name: &str) -> Result<Vec<DBRow>> {
+    let transaction = conn.transaction()?;
+    let mut deleted = vec![];
+    for row in rows {
+        let rows_changed = delete_row(&transaction, row, table_name)?;
+        deleted.extend(std::iter::repeat_n(row.clone(), rows_changed));
+    }
+    transaction.commit()?;
+
+    Ok(deleted)
+}
+
+/// Deletes every row of `table_name` matching `filter` in a single transaction. Returns how
+/// many rows were deleted, and the rows so they can be restored unless there were more than
+/// `UNDO_ROW_LIMIT`.
+pub fn delete_matching_from_table(
+    table_name: &str,
+    filter: &TableFilter,
+) -> Result<(usize, Option<Vec<DBRow>>)> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    delete_matching(&mut conn, table_name, filter, UNDO_ROW_LIMIT)
+}
+
+fn delete_matching(
+    conn: &mut Connection,
+    table_name: &str,
+    filter: &TableFilter,
+    undo_row_limit: usize,
+) -> Result<(usize, Option<Vec<DBRow>>)> {
+    let transaction = conn.transaction()?;
+    let matching = count_in_table(&transaction, table_name, filter)?;
+    let deleted = match matching > undo_row_limit {
+        true => None,
+        false => {
+            let mut deleted = vec![];
+            visit_rows_in_table(&transaction, table_name, filter, |row| {
+                deleted.push(row.clone());
+                Ok(())
+            })?;
+            Some(deleted)
+        }
+    };
+
+    let columns = query_table_columns(&transaction, table_name)?;
+    let (where_clause, params) = filter.where_clause(&columns)?;
+    let rows_changed = transaction.execute(
+        &format!(
+            "DELETE FROM {} {};",
+            quote_identifier(table_name),
+            where_clause
+        ),
+        params_from_iter(params.iter()),
+    )?;
+    if rows_changed != matching {
+        return Err(Error::other("Rows changed while deleting, nothing was deleted.").into());
+    }
+    transaction.commit()?;
+
+    Ok((rows_changed, deleted))
+}
+
+/// Number of rows of `table_name` matching `filter`.
+pub fn count_rows_in_table(table_name: &str, filter: &TableFilter) -> Result<usize> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    count_in_table(&conn, table_name, filter)
+}
+
+fn count_in_table(conn: &Connection, table_name: &str, filter: &TableFilter) -> Result<usize> {
+    let columns = query_table_columns(conn, table_name)?;
+    let (where_clause, params) = filter.where_clause(&columns)?;
+    let count = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM {} {};",
+            quote_identifier(table_name),
+            where_clause
+        ),
+        params_from_iter(params.iter()),
+        |row| row.get::<usize, i64>(0),
+    )?;
+
+    Ok(count as usize)
+}
+
+/// Inserts previously deleted `rows` back into `table_name` in a single transaction, keeping
+/// their rowid where it is still free. SQLite hands freed rowids to new rows, so a row whose
+/// rowid was taken meanwhile gets a new one.
+pub fn restore_rows_to_table(rows: &[DBRow], table_name: &str) -> Result<usize> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    restore_rows(&mut conn, rows, table_name)
+}
+
+fn restore_rows(conn: &mut Connection, rows: &[DBRow], table_name: &str) -> Result<usize> {
+    let transaction = conn.transaction()?;
+    for row in rows {
+        if let Some(rowid) = row.rowid
+            && let Some(alias) = rowid_alias(&row.columns)
+        {
+            let mut columns = row.columns.to_vec();
+            columns.push(TableColumn {
+                name: alias.to_owned(),
+                decl_type: "INTEGER".to_owned(),
+                primary_key: false,
+            });
+            let mut values = row.values.clone();
+            values.push(ColumnKind::INTEGER(rowid));
+            if insert(
+                &transaction,
+                "INSERT OR IGNORE",
+                &columns,
+                &values,
+                table_name,
+            )? > 0
+            {
+                continue;
+            }
+        }
+        insert_row(&transaction, &row.columns, &row.values, table_name)?;
+    }
+    transaction.commit()?;
+
+    Ok(rows.len())
+}
+
+/// Sets the columns of `row` in `table_name` to `new_values`.
+pub fn update_row_in_table(
+    row: &DBRow,
+    new_values: &[ColumnKind],
+    table_name: &str,
+) -> Result<usize> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    update_row(&conn, row, new_values, table_name)
+}
+
+fn update_row(
+    conn: &Connection,
+    row: &DBRow,
+    new_values: &[ColumnKind],
+    table_name: &str,
+) -> Result<usize> {
+    let assignments: Vec<String> = row
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| format!("{} = ?{}", quote_identifier(&column.name), idx + 1))
+        .collect();
+    let (where_clause, params) = row_condition(row, row.columns.len() + 1);
+    let query = format!(
+        "UPDATE {} SET {} {};",
+        quote_identifier(table_name),
+        assignments.join(", "),
+        where_clause
+    );
+
+    let rows_changed = conn.execute(
+        &query,
+        params_from_iter(new_values.iter().chain(params.iter())),
+    )?;
+
+    Ok(rows_changed)
+}
+
+/// Inserts a single row with a value for each of `columns`.
+pub fn insert_row_into_table(
+    columns: &[TableColumn],
+    values: &[ColumnKind],
+    table_name: &str,
+) -> Result<usize> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    insert_row(&conn, columns, values, table_name)
+}
+
+fn insert_row(
+    conn: &Connection,
+    columns: &[TableColumn],
+    values: &[ColumnKind],
+    table_name: &str,
+) -> Result<usize> {
+    insert(conn, "INSERT", columns, values, table_name)
+}
+
+/// Runs `verb`, `INSERT` or one of its `OR` forms, with `values` for `columns`.
+fn insert(
+    conn: &Connection,
+    verb: &str,
+    columns: &[TableColumn],
+    values: &[ColumnKind],
+    table_name: &str,
+) -> Result<usize> {
+    let names: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect();
+    let placeholders: Vec<String> = (1..=columns.len()).map(|idx| format!("?{}", idx)).collect();
+    let query = format!(
+        "{} INTO {} ({}) VALUES ({});",
+        verb,
+        quote_identifier(table_name),
+        names.join(", "),
+        placeholders.join(", ")
+    );
+
+    let rows_changed = conn.execute(&query, params_from_iter(values.iter()))?;
+
+    Ok(rows_changed)
+}
+
+/// Rows of `table_name` with the given timestamp and topic, with their rowid so duplicates
+/// can be told apart.
+pub fn get_rows_at(table_name: &str, timestamp: u64, topic: &str) -> Result<Vec<DBRow>> {
     let conn = Connection::open(&ARGS.db_path)?;
-    let mut statement = conn.prepare(&format!("SELECT * FROM {};", table_name))?;
-
-    let rows_iter = statement.query_map([], |row| {
-        let val = row
-            .get::<usize, ColumnKind>(2)
-            .unwrap_or(ColumnKind::FLOAT(0.));
-        let curr_row = DBRow {
-            timestamp: row.get(0).unwrap_or(0),
-            topic: row.get(1).unwrap_or("".to_owned()),
-            value: format!("{}", val),
-        };
-        Ok(curr_row)
+    query_rows_at(&conn, table_name, timestamp, topic)
+}
+
+fn query_rows_at(
+    conn: &Connection,
+    table_name: &str,
+    timestamp: u64,
+    topic: &str,
+) -> Result<Vec<DBRow>> {
+    let columns = Arc::new(query_table_columns(conn, table_name)?);
+    let mut column_list: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect();
+    if has_rowid(conn, table_name)?
+        && let Some(rowid) = rowid_alias(&columns)
+    {
+        column_list.push(rowid.to_owned());
+    }
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM {} WHERE timestamp = ?1 and topic = ?2;",
+        column_list.join(", "),
+        quote_identifier(table_name)
+    ))?;
+
+    let rows = statement
+        .query_map(params![timestamp, topic], |row| read_row(row, &columns))?
+        .collect::<Result<Vec<DBRow>, rusqlite::Error>>()?;
+
+    Ok(rows)
+}
+
+pub fn get_table_columns(table_name: &str) -> Result<Vec<TableColumn>> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_table_columns(&conn, table_name)
+}
+
+pub(crate) fn query_table_columns(conn: &Connection, table_name: &str) -> Result<Vec<TableColumn>> {
+    let mut statement =
+        conn.prepare("SELECT name, type, pk FROM pragma_table_info(?1) ORDER BY cid;")?;
+    let columns_iter = statement.query_map([table_name], |row| {
+        Ok(TableColumn {
+            name: row.get(0)?,
+            decl_type: row.get(1)?,
+            primary_key: row.get::<usize, i64>(2)? > 0,
+        })
     })?;
 
+    let columns: Vec<TableColumn> = columns_iter.collect::<Result<_, rusqlite::Error>>()?;
+
+    if columns.is_empty() {
+        return Err(Error::other(format!("Table {} not found.", table_name)).into());
+    }
+
+    Ok(columns)
+}
+
+pub fn get_all_from_table(table_name: &str) -> Result<Vec<DBRow>> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_all_from_table(&conn, table_name)
+}
+
+fn query_all_from_table(conn: &Connection, table_name: &str) -> Result<Vec<DBRow>> {
+    let columns = Arc::new(query_table_columns(conn, table_name)?);
+    let column_list: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect();
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM {};",
+        column_list.join(", "),
+        quote_identifier(table_name)
+    ))?;
+
+    let rows_iter = statement.query_map([], |row| read_row(row, &columns))?;
+
     let rows: Result<Vec<DBRow>, rusqlite::Error> = rows_iter.into_iter().collect();
 
     if let Ok(valid_rows) = rows {
         return Ok(valid_rows);
     }
 
-    return Err(Error::new(
-        ErrorKind::Other,
-        "Something went wrong while getting data from the table",
-    )
-    .into());
+    Err(Error::other("Something went wrong while getting data from the table").into())
 }
 
-fn fix_str_len(string: &str, len: usize) -> String {
-    let mut new_string = String::new();
-    let mut chars = string.chars();
-    // let mut char_indicies = string.char_indices();
-    for _ in 0..len {
-        let char = chars.nth(0);
-        if let Some(char) = char {
-            new_string.push(char);
-        } else {
-            new_string.push(' ');
+/// Rows of a table screen that should be shown. Turned into a `WHERE` clause so filtering
+/// happens in SQLite instead of in memory.
+#[derive(Clone, Default)]
+pub struct TableFilter {
+    /// Substring matched against every column, case insensitive for ASCII.
+    pub text: String,
+    /// Range of the `timestamp` column.
+    pub time_range: TimeRange,
+    /// MQTT topic filter for the `topic` column, with `+` and `#` wildcards.
+    pub topic: String,
+    /// Order of the rows, by insertion when not set.
+    pub sort: Option<TableSort>,
+}
+
+/// Column the rows of a table screen are ordered by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableSort {
+    pub column: String,
+    pub descending: bool,
+}
+
+impl TableFilter {
+    /// Whether every row matches, the sort order aside.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.time_range.is_empty() && self.topic.is_empty()
+    }
+
+    /// Builds the `WHERE` clause for `columns`, or an empty string when nothing is filtered.
+    /// Placeholders are numbered from `?1` in the order of the returned parameters.
+    pub(crate) fn where_clause(
+        &self,
+        columns: &[TableColumn],
+    ) -> Result<(String, Vec<ColumnKind>)> {
+        let mut conditions: Vec<String> = vec![];
+        let mut params: Vec<ColumnKind> = vec![];
+
+        if !self.text.is_empty() {
+            params.push(ColumnKind::STRING(format!("%{}%", escape_like(&self.text))));
+            let placeholder = params.len();
+
+            let text_conditions: Vec<String> = columns
+                .iter()
+                .map(|column| {
+                    let name = quote_identifier(&column.name);
+                    // Match what the table screen shows for timestamps, not the raw epoch.
+                    let shown = if is_timestamp_column(column) {
+                        format!(
+                            "CASE typeof({0}) WHEN 'integer' THEN datetime({0}, 'unixepoch') ELSE {0} END",
+                            name
+                        )
+                    } else {
+                        name
+                    };
+                    format!("CAST({} AS TEXT) LIKE ?{} ESCAPE '\\'", shown, placeholder)
+                })
+                .collect();
+            conditions.push(format!("({})", text_conditions.join(" OR ")));
+        }
+
+        if !self.time_range.is_empty() {
+            let timestamp_column = columns
+                .iter()
+                .find(|column| is_timestamp_column(column))
+                .ok_or(Error::other("This table has no timestamp column."))?;
+            let name = quote_identifier(&timestamp_column.name);
+
+            if let Some(from) = self.time_range.from {
+                params.push(ColumnKind::INTEGER(from));
+                conditions.push(format!("{} >= ?{}", name, params.len()));
+            }
+            if let Some(to) = self.time_range.to {
+                params.push(ColumnKind::INTEGER(to));
+                conditions.push(format!("{} < ?{}", name, params.len()));
+            }
+        }
+
+        if !self.topic.is_empty() {
+            let topic_column = columns
+                .iter()
+                .find(|column| column.name == "topic")
+                .ok_or(Error::other("This table has no topic column."))?;
+            let name = quote_identifier(&topic_column.name);
+            conditions.push(topic_condition(&name, &self.topic, &mut params)?);
         }
+
+        if conditions.is_empty() {
+            return Ok(("".to_owned(), params));
+        }
+
+        Ok((format!("WHERE {}", conditions.join(" AND ")), params))
+    }
+}
+
+/// Condition matching `column` against an MQTT topic filter. Levels are compared one by
+/// one, so the match is case sensitive and `+` never spans a `/`.
+fn topic_condition(column: &str, pattern: &str, params: &mut Vec<ColumnKind>) -> Result<String> {
+    validate_filter(pattern)?;
+    let levels: Vec<&str> = pattern.split('/').collect();
+
+    if !levels.iter().any(|level| *level == "+" || *level == "#") {
+        params.push(ColumnKind::STRING(pattern.to_owned()));
+        return Ok(format!("{} = ?{}", column, params.len()));
     }
 
-    new_string
+    let (levels, any_depth) = match levels.split_last() {
+        Some((&"#", parents)) => (parents, true),
+        _ => (levels.as_slice(), false),
+    };
+    let slashes = format!("(length({0}) - length(replace({0}, '/', '')))", column);
+    let mut conditions = vec![match (any_depth, levels.len()) {
+        (true, 0) => format!("{} IS NOT NULL", column),
+        // `a/#` matches `a` itself too.
+        (true, depth) => format!("{} >= {}", slashes, depth - 1),
+        (false, depth) => format!("{} = {}", slashes, depth - 1),
+    }];
+
+    // Narrows the search to the topics starting with the levels before the first wildcard,
+    // so an index on the topic can be used. `0` is the character after `/`.
+    let prefix = levels
+        .iter()
+        .take_while(|level| **level != "+")
+        .copied()
+        .collect::<Vec<&str>>()
+        .join("/");
+    if !prefix.is_empty() {
+        params.push(ColumnKind::STRING(prefix.clone()));
+        params.push(ColumnKind::STRING(format!("{}0", prefix)));
+        conditions.push(format!(
+            "{0} >= ?{1} AND {0} < ?{2}",
+            column,
+            params.len() - 1,
+            params.len()
+        ));
+    }
+
+    // The topic as a JSON array of its levels.
+    let split = format!(
+        "'[' || replace(json_quote({}), '/', '\",\"') || ']'",
+        column
+    );
+    levels
+        .iter()
+        .enumerate()
+        .filter(|(_, level)| **level != "+")
+        .for_each(|(idx, level)| {
+            params.push(ColumnKind::STRING(level.to_string()));
+            conditions.push(format!(
+                "json_extract({}, '$[{}]') = ?{}",
+                split,
+                idx,
+                params.len()
+            ));
+        });
+
+    Ok(format!("({})", conditions.join(" AND ")))
 }
 
-impl DBRow {
-    fn fix_col_lengths(&self, len: usize) -> Self {
-        DBRow {
-            timestamp: self.timestamp,
-            topic: fix_str_len(&self.topic, len),
-            value: fix_str_len(&self.value, len),
+/// Escapes `%`, `_` and `\` for a `LIKE` pattern with `ESCAPE '\'`.
+pub fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub(crate) fn is_timestamp_column(column: &TableColumn) -> bool {
+    column.name.eq_ignore_ascii_case("timestamp")
+}
+
+/// Reads at most `limit` rows of `table_name` matching `filter`, newest first, continuing
+/// after `last`, the last row of the previous page. Rows inserted meanwhile do not shift the
+/// page. Tables without a rowid skip the first `offset` matches instead.
+pub fn get_page_from_table(
+    table_name: &str,
+    filter: &TableFilter,
+    last: Option<&DBRow>,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<DBRow>> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_page_from_table(&conn, table_name, filter, last, offset, limit)
+}
+
+fn query_page_from_table(
+    conn: &Connection,
+    table_name: &str,
+    filter: &TableFilter,
+    last: Option<&DBRow>,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<DBRow>> {
+    let last = last.filter(|row| row.rowid.is_some());
+    let offset = match last {
+        Some(_) => 0,
+        None => offset,
+    };
+    let (sql, mut params, columns) = build_select(conn, table_name, filter, true, None, last)?;
+    let param_count = params.len();
+    params.push(ColumnKind::INTEGER(limit as i64));
+    params.push(ColumnKind::INTEGER(offset as i64));
+
+    let mut statement = conn.prepare(&format!(
+        "{} LIMIT ?{} OFFSET ?{};",
+        sql,
+        param_count + 1,
+        param_count + 2,
+    ))?;
+
+    let rows = statement
+        .query_map(params_from_iter(params.iter()), |row| {
+            read_row(row, &columns)
+        })?
+        .collect::<Result<Vec<DBRow>, rusqlite::Error>>()?;
+
+    Ok(rows)
+}
+
+/// Rows of `table_name` matching `filter` inserted after `rowid`, newest first. Returns at
+/// most `limit` rows.
+pub fn get_rows_after(
+    table_name: &str,
+    filter: &TableFilter,
+    rowid: i64,
+    limit: usize,
+) -> Result<Vec<DBRow>> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_rows_after(&conn, table_name, filter, rowid, limit)
+}
+
+fn query_rows_after(
+    conn: &Connection,
+    table_name: &str,
+    filter: &TableFilter,
+    rowid: i64,
+    limit: usize,
+) -> Result<Vec<DBRow>> {
+    let (sql, mut params, columns) =
+        build_select(conn, table_name, filter, true, Some(rowid), None)?;
+    params.push(ColumnKind::INTEGER(limit as i64));
+
+    let mut statement = conn.prepare(&format!("{} LIMIT ?{};", sql, params.len()))?;
+    let rows = statement
+        .query_map(params_from_iter(params.iter()), |row| {
+            read_row(row, &columns)
+        })?
+        .collect::<Result<Vec<DBRow>, rusqlite::Error>>()?;
+
+    Ok(rows)
+}
+
+/// Tells whether another connection committed to the database since the last check, using
+/// `PRAGMA data_version`. Cheap enough to poll.
+pub struct ChangeWatcher {
+    conn: Connection,
+    data_version: i64,
+}
+
+impl ChangeWatcher {
+    pub fn open() -> Result<Self> {
+        Self::watch(Connection::open(&ARGS.db_path)?)
+    }
+
+    fn watch(conn: Connection) -> Result<Self> {
+        let data_version = read_data_version(&conn)?;
+        Ok(ChangeWatcher { conn, data_version })
+    }
+
+    pub fn has_changed(&mut self) -> Result<bool> {
+        let data_version = read_data_version(&self.conn)?;
+        let changed = data_version != self.data_version;
+        self.data_version = data_version;
+        Ok(changed)
+    }
+}
+
+fn read_data_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA data_version;", [], |row| row.get(0))?)
+}
+
+/// Calls `on_row` for every row of `table_name` matching `filter`, oldest first, without
+/// holding the whole table in memory. Returns the number of rows visited.
+pub fn for_each_row_in_table(
+    table_name: &str,
+    filter: &TableFilter,
+    on_row: impl FnMut(&DBRow) -> Result<()>,
+) -> Result<usize> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    visit_rows_in_table(&conn, table_name, filter, on_row)
+}
+
+fn visit_rows_in_table(
+    conn: &Connection,
+    table_name: &str,
+    filter: &TableFilter,
+    mut on_row: impl FnMut(&DBRow) -> Result<()>,
+) -> Result<usize> {
+    let (sql, params, columns) = build_select(conn, table_name, filter, false, None, None)?;
+    let mut statement = conn.prepare(&sql)?;
+    let mut rows = statement.query(params_from_iter(params.iter()))?;
+
+    let mut row_count = 0;
+    while let Some(row) = rows.next()? {
+        on_row(&read_row(row, &columns)?)?;
+        row_count += 1;
+    }
+
+    Ok(row_count)
+}
+
+/// Builds a `SELECT` of every column of `table_name` matching `filter`, ordered by insertion
+/// where the table allows it. Returns the query, its parameters and the selected columns.
+/// With `after_rowid` only rows inserted after that rowid are selected. With `page_after`,
+/// newest first, only rows ordered after that row are selected.
+fn build_select(
+    conn: &Connection,
+    table_name: &str,
+    filter: &TableFilter,
+    newest_first: bool,
+    after_rowid: Option<i64>,
+    page_after: Option<&DBRow>,
+) -> Result<(String, Vec<ColumnKind>, Arc<Vec<TableColumn>>)> {
+    let columns = Arc::new(query_table_columns(conn, table_name)?);
+    let mut column_list: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(&column.name))
+        .collect();
+    let (mut where_clause, mut params) = filter.where_clause(&columns)?;
+    let rowid = match has_rowid(conn, table_name)? {
+        true => rowid_alias(&columns),
+        false => None,
+    };
+    if let Some(after_rowid) = after_rowid {
+        let rowid = rowid.ok_or(Error::other(
+            "This table has no rowid, so new rows can not be told apart.",
+        ))?;
+        params.push(ColumnKind::INTEGER(after_rowid));
+        let condition = format!("{} > ?{}", rowid, params.len());
+        where_clause = match where_clause.is_empty() {
+            true => format!("WHERE {}", condition),
+            false => format!("{} AND {}", where_clause, condition),
+        };
+    }
+    let mut order: Vec<String> = vec![];
+    if let Some(sort) = &filter.sort {
+        if !columns.iter().any(|column| column.name == sort.column) {
+            return Err(Error::other(format!("This table has no {} column.", sort.column)).into());
         }
+        let direction = if sort.descending { "DESC" } else { "ASC" };
+        order.push(format!("{} {}", quote_identifier(&sort.column), direction));
+    }
+    // Rows that sort the same stay in insertion order.
+    if let Some(rowid) = rowid {
+        let direction = if newest_first { "DESC" } else { "ASC" };
+        order.push(format!("{} {}", rowid, direction));
+    }
+    if let Some((row, rowid)) = page_after.zip(rowid) {
+        let condition = page_after_condition(row, rowid, filter.sort.as_ref(), &mut params);
+        where_clause = match where_clause.is_empty() {
+            true => format!("WHERE {}", condition),
+            false => format!("{} AND {}", where_clause, condition),
+        };
+    }
+    let order_clause = match order.is_empty() {
+        true => "".to_owned(),
+        false => format!("ORDER BY {}", order.join(", ")),
+    };
+    // Selected last so `read_row` can pick it up.
+    if let Some(rowid) = rowid {
+        column_list.push(rowid.to_owned());
+    }
+
+    let sql = format!(
+        "SELECT {} FROM {} {} {}",
+        column_list.join(", "),
+        quote_identifier(table_name),
+        where_clause,
+        order_clause,
+    );
+
+    Ok((sql, params, columns))
+}
+
+/// Matches the rows that come after `row` when ordered by `sort` and then newest first. NULL
+/// sorts before any value, so it comes first ascending and last descending.
+fn page_after_condition(
+    row: &DBRow,
+    rowid: &str,
+    sort: Option<&TableSort>,
+    params: &mut Vec<ColumnKind>,
+) -> String {
+    params.push(ColumnKind::INTEGER(row.rowid.unwrap_or_default()));
+    let older = format!("{} < ?{}", rowid, params.len());
+    let Some(sort) = sort else {
+        return older;
+    };
+
+    let column = quote_identifier(&sort.column);
+    match (row.get(&sort.column), sort.descending) {
+        (None | Some(ColumnKind::NULL), false) => {
+            format!("({0} IS NOT NULL OR {1})", column, older)
+        }
+        (None | Some(ColumnKind::NULL), true) => format!("({0} IS NULL AND {1})", column, older),
+        (Some(value), descending) => {
+            params.push(value.clone());
+            let (comparison, nulls) = match descending {
+                false => (">", String::new()),
+                true => ("<", format!(" OR {} IS NULL", column)),
+            };
+            format!(
+                "({0} {1} ?{2}{3} OR ({0} = ?{2} AND {4}))",
+                column,
+                comparison,
+                params.len(),
+                nulls,
+                older
+            )
+        }
+    }
+}
+
+/// Views and `WITHOUT ROWID` tables can not be ordered by insertion.
+fn has_rowid(conn: &Connection, table_name: &str) -> Result<bool> {
+    let mut statement = conn.prepare("SELECT type, sql FROM sqlite_master WHERE name = ?1;")?;
+    let (kind, sql) = statement
+        .query_row([table_name], |row| {
+            Ok((
+                row.get::<usize, String>(0)?,
+                row.get::<usize, Option<String>>(1)?,
+            ))
+        })
+        .unwrap_or(("view".to_owned(), None));
+
+    Ok(kind == "table"
+        && !sql
+            .unwrap_or_default()
+            .to_uppercase()
+            .contains("WITHOUT ROWID"))
+}
+
+/// A name that refers to the rowid of a table with `columns`. Columns can shadow any of the
+/// aliases, if they shadow all of them the rowid can not be selected.
+fn rowid_alias(columns: &[TableColumn]) -> Option<&'static str> {
+    ["rowid", "_rowid_", "oid"].into_iter().find(|alias| {
+        !columns
+            .iter()
+            .any(|column| column.name.eq_ignore_ascii_case(alias))
+    })
+}
+
+/// Reads the values of `columns`. A trailing extra column is the rowid added by
+/// `build_select`.
+fn read_row(row: &rusqlite::Row, columns: &Arc<Vec<TableColumn>>) -> rusqlite::Result<DBRow> {
+    let values = (0..columns.len())
+        .map(|idx| row.get::<usize, ColumnKind>(idx))
+        .collect::<Result<Vec<ColumnKind>, rusqlite::Error>>()?;
+    let rowid = if row.as_ref().column_count() > columns.len() {
+        row.get::<usize, Option<i64>>(columns.len())?
+    } else {
+        None
+    };
+    Ok(DBRow {
+        columns: columns.clone(),
+        values,
+        rowid,
+    })
+}
+
+pub enum QueryResult {
+    Rows {
+        columns: Arc<Vec<TableColumn>>,
+        rows: Vec<DBRow>,
+        /// More rows were left out after `QUERY_ROW_LIMIT`.
+        truncated: bool,
+    },
+    RowsAffected(usize),
+}
+
+/// Runs a single user supplied SQL statement. Statements without result columns are
+/// executed and report the number of rows they changed. Only the first `QUERY_ROW_LIMIT`
+/// rows of a result are read.
+pub fn run_query(sql: &str) -> Result<QueryResult> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    execute_query(&conn, sql, QUERY_ROW_LIMIT)
+}
+
+fn execute_query(conn: &Connection, sql: &str, row_limit: usize) -> Result<QueryResult> {
+    let mut statement = conn.prepare(sql)?;
+
+    if statement.column_count() == 0 {
+        return Ok(QueryResult::RowsAffected(statement.execute([])?));
     }
+
+    let columns: Arc<Vec<TableColumn>> = Arc::new(
+        statement
+            .column_names()
+            .iter()
+            .map(|name| TableColumn {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect(),
+    );
+
+    // One row past the limit tells whether there were more.
+    let mut rows = statement
+        .query_map([], |row| read_row(row, &columns))?
+        .take(row_limit + 1)
+        .collect::<Result<Vec<DBRow>, rusqlite::Error>>()?;
+    let truncated = rows.len() > row_limit;
+    rows.truncate(row_limit);
+
+    Ok(QueryResult::Rows {
+        columns,
+        rows,
+        truncated,
+    })
 }
 
 pub fn get_tables() -> Result<Vec<String>> {
     let conn = Connection::open(&ARGS.db_path)?;
-    let mut statement = conn.prepare("SELECT name FROM sqlite_master WHERE type='table';")?;
+    let mut statement = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') ORDER BY type, name;",
+    )?;
     let tables_iter = statement.query_map([], |row| row.get::<usize, String>(0))?;
 
     let tables: Result<Vec<String>, rusqlite::Error> = tables_iter.into_iter().collect();
@@ -154,31 +1131,821 @@ pub fn get_tables() -> Result<Vec<String>> {
         return Ok(tables_vec);
     }
 
-    Err(Error::new(ErrorKind::Other, "Could not get db rows.").into())
+    Err(Error::other("Could not get db rows.").into())
+}
+
+/// The tables the migrations create and the SubStore service writes to.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DataTable {
+    Measurements,
+    Logs,
+}
+
+impl DataTable {
+    pub const ALL: [DataTable; 2] = [DataTable::Measurements, DataTable::Logs];
+    pub const COLUMN_NAMES: [&str; 3] = ["timestamp", "topic", "value"];
+
+    pub fn get_table_name(&self) -> &str {
+        match self {
+            DataTable::Measurements => "MEASUREMENTS",
+            DataTable::Logs => "LOGS",
+        }
+    }
+
+    pub fn from_table_name(table_name: &str) -> Option<DataTable> {
+        DataTable::ALL
+            .into_iter()
+            .find(|table| table.get_table_name() == table_name)
+    }
+
+    pub fn get_columns(&self) -> Arc<Vec<TableColumn>> {
+        let value_type = match self {
+            DataTable::Measurements => "float",
+            DataTable::Logs => "varchar(255)",
+        };
+        let columns = DataTable::COLUMN_NAMES
+            .iter()
+            .zip(["int", "varchar(255)", value_type])
+            .map(|(name, decl_type)| TableColumn {
+                name: name.to_string(),
+                decl_type: decl_type.to_owned(),
+                ..Default::default()
+            })
+            .collect();
+        Arc::new(columns)
+    }
+
+    /// Converts text for `column` into the value stored in this table. Timestamps may be unix
+    /// seconds or anything the time filter accepts. MEASUREMENTS values have to be numbers.
+    pub fn parse_value(&self, column: &str, raw: &str) -> Result<ColumnKind> {
+        let table_column = self
+            .get_columns()
+            .iter()
+            .find(|table_column| table_column.name == column)
+            .cloned()
+            .ok_or(Error::other(format!("Unknown column: {}", column)))?;
+
+        // Every row needs a time and a topic.
+        match table_column.parse_value(raw.trim())? {
+            ColumnKind::NULL => Err(Error::other(format!("{} is empty", column)).into()),
+            ColumnKind::STRING(val) if column == "topic" && val.is_empty() => {
+                Err(Error::other("topic is empty").into())
+            }
+            value => Ok(value),
+        }
+    }
 }
 
+/// Inserts `rows` into `table` in a single transaction. Values are in the order of
+/// `DataTable::COLUMN_NAMES`.
+pub fn insert_rows(table: DataTable, rows: &[Vec<ColumnKind>]) -> Result<usize> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    insert_rows_with(&mut conn, table, rows)
+}
+
+fn insert_rows_with(
+    conn: &mut Connection,
+    table: DataTable,
+    rows: &[Vec<ColumnKind>],
+) -> Result<usize> {
+    let transaction = conn.transaction()?;
+    {
+        let mut statement = transaction.prepare(&format!(
+            "INSERT INTO {} (timestamp, topic, value) VALUES (?1, ?2, ?3);",
+            table.get_table_name()
+        ))?;
+        for row in rows {
+            statement.execute(params_from_iter(row.iter()))?;
+        }
+    }
+    transaction.commit()?;
+
+    Ok(rows.len())
+}
+
+/// Creates the tables or brings an existing database up to the latest schema.
 pub fn setup_db() -> Result<()> {
-    let connection = Connection::open(&ARGS.db_path)?;
-    connection.execute(
-        "
-        CREATE TABLE if not exists MEASUREMENTS (
-                timestamp int,
-                topic varchar(255),
-                value float
+    run_migrations()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::migrations::{MIGRATIONS, migrate};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        conn.execute_batch(
+            "
+            CREATE TABLE DEVICES (id integer, name text, battery real, firmware blob);
+            INSERT INTO DEVICES VALUES (1, 'kitchen', 87.5, x'0102'), (2, 'garage', NULL, NULL);
+            CREATE VIEW DEVICE_NAMES AS SELECT name FROM DEVICES;
+            ",
         )
-        ",
-        (),
-    )?;
+        .expect("Should be able to create test tables");
+        conn
+    }
+
+    #[test]
+    fn should_read_columns_of_any_table() {
+        let conn = setup_test_db();
+        let columns = query_table_columns(&conn, "DEVICES").expect("Should read columns");
+
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        let types: Vec<&str> = columns.iter().map(|c| c.decl_type.as_str()).collect();
+        assert_eq!(names, vec!["id", "name", "battery", "firmware"]);
+        assert_eq!(types, vec!["INTEGER", "TEXT", "REAL", "BLOB"]);
+    }
+
+    #[test]
+    fn should_read_rows_of_any_table() {
+        let conn = setup_test_db();
+        let rows = query_all_from_table(&conn, "DEVICES").expect("Should read rows");
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].values,
+            vec![
+                ColumnKind::INTEGER(1),
+                ColumnKind::STRING("kitchen".to_owned()),
+                ColumnKind::FLOAT(87.5),
+                ColumnKind::BLOB(vec![1, 2]),
+            ]
+        );
+        assert_eq!(rows[1].get("battery"), Some(&ColumnKind::NULL));
+    }
+
+    #[test]
+    fn should_read_rows_of_views() {
+        let conn = setup_test_db();
+        let rows = query_all_from_table(&conn, "DEVICE_NAMES").expect("Should read view");
+
+        assert_eq!(rows[0].columns[0].name, "name");
+        assert_eq!(rows[1].display_value(0), "garage");
+    }
 
-    connection.execute(
-        "
-        CREATE TABLE if not exists LOGS (
-                timestamp int,
-                topic varchar(255),
-                value varchar(255)
+    #[test]
+    fn should_return_rows_for_select_query() {
+        let conn = setup_test_db();
+        let result = execute_query(
+            &conn,
+            "SELECT name, battery * 2 AS doubled FROM DEVICES;",
+            10,
         )
-        ",
-        (),
-    )?;
-    Ok(())
+        .expect("Should run query");
+
+        let QueryResult::Rows {
+            rows, truncated, ..
+        } = result
+        else {
+            panic!("Expected rows from a select query");
+        };
+        assert_eq!(rows[0].columns[1].name, "doubled");
+        assert_eq!(rows[0].get("doubled"), Some(&ColumnKind::FLOAT(175.)));
+        assert!(!truncated);
+
+        let QueryResult::Rows {
+            rows, truncated, ..
+        } = execute_query(&conn, "SELECT * FROM DEVICES;", 1).expect("Should run query")
+        else {
+            panic!("Expected rows from a select query");
+        };
+        assert_eq!(rows.len(), 1);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn should_return_rows_affected_for_dml_query() {
+        let conn = setup_test_db();
+        let result = execute_query(&conn, "UPDATE DEVICES SET name = 'shed' WHERE id > 0;", 10)
+            .expect("Should run query");
+
+        assert!(matches!(result, QueryResult::RowsAffected(2)));
+    }
+
+    #[test]
+    fn should_page_newest_first() {
+        let conn = setup_test_db();
+        let filter = TableFilter::default();
+
+        let first_page =
+            query_page_from_table(&conn, "DEVICES", &filter, None, 0, 1).expect("Should read page");
+        let second_page = query_page_from_table(&conn, "DEVICES", &filter, first_page.last(), 1, 1)
+            .expect("Should read page");
+
+        assert_eq!(
+            first_page[0].get("name"),
+            Some(&ColumnKind::STRING("garage".to_owned()))
+        );
+        assert_eq!(
+            second_page[0].get("name"),
+            Some(&ColumnKind::STRING("kitchen".to_owned()))
+        );
+    }
+
+    #[test]
+    fn should_page_past_rows_inserted_meanwhile() {
+        let conn = setup_test_db();
+        let filter = TableFilter::default();
+
+        let first_page =
+            query_page_from_table(&conn, "DEVICES", &filter, None, 0, 1).expect("Should read page");
+        conn.execute("INSERT INTO DEVICES VALUES (3, 'attic', 87.5, NULL);", ())
+            .expect("Should insert row");
+        let second_page =
+            query_page_from_table(&conn, "DEVICES", &filter, first_page.last(), 1, 10)
+                .expect("Should read page");
+
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(
+            second_page[0].get("name"),
+            Some(&ColumnKind::STRING("kitchen".to_owned()))
+        );
+    }
+
+    #[test]
+    fn should_page_sorted_rows_one_at_a_time() {
+        let conn = setup_test_db();
+        conn.execute_batch(
+            "INSERT INTO DEVICES VALUES (3, 'attic', 87.5, NULL), (4, 'hall', NULL, NULL);",
+        )
+        .expect("Should insert rows");
+
+        for descending in [false, true] {
+            let filter = TableFilter {
+                sort: Some(TableSort {
+                    column: "battery".to_owned(),
+                    descending,
+                }),
+                ..Default::default()
+            };
+            let all = query_page_from_table(&conn, "DEVICES", &filter, None, 0, 10)
+                .expect("Should read page");
+            let mut paged: Vec<DBRow> = vec![];
+            while let Some(row) =
+                query_page_from_table(&conn, "DEVICES", &filter, paged.last(), paged.len(), 1)
+                    .expect("Should read page")
+                    .pop()
+            {
+                paged.push(row);
+            }
+
+            let rowids =
+                |rows: &[DBRow]| -> Vec<Option<i64>> { rows.iter().map(|row| row.rowid).collect() };
+            assert_eq!(all.len(), 4);
+            assert_eq!(rowids(&paged), rowids(&all));
+        }
+    }
+
+    #[test]
+    fn should_filter_in_sql() {
+        let conn = setup_test_db();
+        let filter = TableFilter {
+            text: "KITCH".to_owned(),
+            ..Default::default()
+        };
+
+        let rows = query_page_from_table(&conn, "DEVICES", &filter, None, 0, 10)
+            .expect("Should read page");
+        assert_eq!(rows.len(), 1);
+
+        let filter = TableFilter {
+            text: "%".to_owned(),
+            ..Default::default()
+        };
+        let rows = query_page_from_table(&conn, "DEVICES", &filter, None, 0, 10)
+            .expect("Should read page");
+        assert!(
+            rows.is_empty(),
+            "Wildcards in the filter should be matched literally"
+        );
+    }
+
+    #[test]
+    fn should_filter_by_time_range() {
+        let conn = setup_test_db();
+        conn.execute_batch(
+            "
+            CREATE TABLE LOGS (timestamp int, topic varchar(255), value varchar(255));
+            INSERT INTO LOGS VALUES (100, '/a', 'one'), (200, '/b', 'two'), (300, '/a', 'three');
+            ",
+        )
+        .expect("Should be able to create logs table");
+
+        let filter = TableFilter {
+            text: "/a".to_owned(),
+            time_range: TimeRange {
+                from: Some(100),
+                to: Some(300),
+            },
+            ..Default::default()
+        };
+        let rows = query_page_from_table(&conn, "LOGS", &filter, None, 0, 10).expect("Should read");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].get("value"),
+            Some(&ColumnKind::STRING("one".to_owned()))
+        );
+
+        let filter = TableFilter {
+            time_range: TimeRange {
+                from: Some(150),
+                to: None,
+            },
+            ..Default::default()
+        };
+        let rows = query_page_from_table(&conn, "LOGS", &filter, None, 0, 10).expect("Should read");
+        assert_eq!(rows.len(), 2);
+
+        assert!(
+            query_page_from_table(&conn, "DEVICES", &filter, None, 0, 10).is_err(),
+            "Tables without a timestamp column can not be filtered by time"
+        );
+    }
+
+    #[test]
+    fn should_sort_by_any_column() {
+        let conn = setup_test_db();
+        conn.execute("INSERT INTO DEVICES VALUES (3, 'attic', 87.5, NULL);", ())
+            .expect("Should insert row");
+        let names = |sort: Option<TableSort>| -> Vec<String> {
+            let filter = TableFilter {
+                sort,
+                ..Default::default()
+            };
+            query_page_from_table(&conn, "DEVICES", &filter, None, 0, 10)
+                .expect("Should read")
+                .iter()
+                .map(|row| row.display_value(1))
+                .collect()
+        };
+
+        assert_eq!(names(None), vec!["attic", "garage", "kitchen"]);
+        let by_name = TableSort {
+            column: "name".to_owned(),
+            descending: false,
+        };
+        assert_eq!(names(Some(by_name)), vec!["attic", "garage", "kitchen"]);
+        // NULL sorts first, equal values newest first.
+        let by_battery = TableSort {
+            column: "battery".to_owned(),
+            descending: false,
+        };
+        assert_eq!(names(Some(by_battery)), vec!["garage", "attic", "kitchen"]);
+
+        let filter = TableFilter {
+            sort: Some(TableSort {
+                column: "missing".to_owned(),
+                descending: true,
+            }),
+            ..Default::default()
+        };
+        assert!(query_page_from_table(&conn, "DEVICES", &filter, None, 0, 10).is_err());
+    }
+
+    #[test]
+    fn should_read_rows_after_rowid() {
+        let conn = setup_test_db();
+        conn.execute_batch(
+            "INSERT INTO DEVICES VALUES (3, 'hall', 50.0, NULL), (4, 'attic', NULL, NULL);",
+        )
+        .expect("Should insert rows");
+
+        let rows = query_rows_after(&conn, "DEVICES", &TableFilter::default(), 2, 10).unwrap();
+        let rowids: Vec<Option<i64>> = rows.iter().map(|row| row.rowid).collect();
+        assert_eq!(rowids, vec![Some(4), Some(3)]);
+
+        let filter = TableFilter {
+            text: "hall".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            query_rows_after(&conn, "DEVICES", &filter, 1, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            query_rows_after(&conn, "DEVICES", &TableFilter::default(), 1, 2)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(
+            query_rows_after(&conn, "DEVICE_NAMES", &TableFilter::default(), 0, 10).is_err(),
+            "Views have no rowid to compare"
+        );
+    }
+
+    #[test]
+    fn should_notice_commits_of_other_connections() {
+        let path = std::env::temp_dir().join(format!("mqttui-watch-{}.db", std::process::id()));
+        let writer = Connection::open(&path).expect("Should open db file");
+        writer
+            .execute("CREATE TABLE DEVICES (id integer);", ())
+            .unwrap();
+
+        let mut watcher = ChangeWatcher::watch(Connection::open(&path).unwrap()).unwrap();
+        assert!(!watcher.has_changed().unwrap());
+        writer
+            .execute("INSERT INTO DEVICES VALUES (1);", ())
+            .unwrap();
+        assert!(watcher.has_changed().unwrap());
+        assert!(!watcher.has_changed().unwrap());
+
+        drop(watcher);
+        drop(writer);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn should_filter_by_topic_wildcards() {
+        let conn = setup_test_db();
+        conn.execute_batch(
+            "
+            CREATE TABLE LOGS (timestamp int, topic varchar(255), value varchar(255));
+            INSERT INTO LOGS VALUES
+                (1, '/home', 'a'), (2, '/home/kitchen/temp', 'b'), (3, '/home/hall/temp', 'c'),
+                (4, '/home/kitchen', 'd'), (5, '/homes/x', 'e'), (6, '/HOME/kitchen', 'f'),
+                (7, '/home/a/b/temp', 'g');
+            ",
+        )
+        .expect("Should be able to create logs table");
+        let values = |topic: &str| -> Vec<String> {
+            let filter = TableFilter {
+                topic: topic.to_owned(),
+                ..Default::default()
+            };
+            query_page_from_table(&conn, "LOGS", &filter, None, 0, 10)
+                .expect("Should read")
+                .iter()
+                .filter_map(|row| row.get("value").map(|value| value.to_raw_string()))
+                .collect()
+        };
+
+        assert_eq!(values("/home/#"), vec!["g", "d", "c", "b", "a"]);
+        assert_eq!(values("/home/+/temp"), vec!["c", "b"]);
+        assert_eq!(values("/home/+"), vec!["d"]);
+        assert_eq!(values("/+/kitchen/#"), vec!["f", "d", "b"]);
+        assert_eq!(values("/home/kitchen"), vec!["d"]);
+        assert_eq!(values("#").len(), 7);
+
+        let filter = TableFilter {
+            topic: "/home/#/temp".to_owned(),
+            ..Default::default()
+        };
+        assert!(!filter.is_empty());
+        assert!(TableFilter::default().is_empty());
+        assert!(query_page_from_table(&conn, "LOGS", &filter, None, 0, 10).is_err());
+        assert!(
+            query_page_from_table(
+                &conn,
+                "DEVICES",
+                &TableFilter {
+                    topic: "#".to_owned(),
+                    ..Default::default()
+                },
+                None,
+                0,
+                10
+            )
+            .is_err(),
+            "Tables without a topic column can not be filtered by topic"
+        );
+    }
+
+    #[test]
+    fn should_page_views() {
+        let conn = setup_test_db();
+        let rows =
+            query_page_from_table(&conn, "DEVICE_NAMES", &TableFilter::default(), None, 0, 10)
+                .expect("Should read view page");
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn should_validate_values_by_table() {
+        assert_eq!(
+            DataTable::Measurements
+                .parse_value("value", " 21.5")
+                .unwrap(),
+            ColumnKind::FLOAT(21.5)
+        );
+        assert!(
+            DataTable::Measurements
+                .parse_value("value", "warm")
+                .is_err()
+        );
+        assert_eq!(
+            DataTable::Logs.parse_value("value", "warm").unwrap(),
+            ColumnKind::STRING("warm".to_owned())
+        );
+        assert_eq!(
+            DataTable::Logs
+                .parse_value("timestamp", "1700000000")
+                .unwrap(),
+            ColumnKind::INTEGER(1700000000)
+        );
+        assert!(DataTable::Logs.parse_value("topic", "").is_err());
+    }
+
+    #[test]
+    fn should_insert_rows_in_one_transaction() {
+        let mut conn = Connection::open_in_memory().expect("Should open db");
+        migrate(&mut conn, MIGRATIONS).expect("Should create tables");
+        let rows = vec![
+            vec![
+                ColumnKind::INTEGER(1),
+                ColumnKind::STRING("/a".to_owned()),
+                ColumnKind::STRING("on".to_owned()),
+            ],
+            vec![
+                ColumnKind::INTEGER(2),
+                ColumnKind::STRING("/a".to_owned()),
+                ColumnKind::STRING("off".to_owned()),
+            ],
+        ];
+
+        let inserted = insert_rows_with(&mut conn, DataTable::Logs, &rows).expect("Should insert");
+        assert_eq!(inserted, 2);
+        let stored = query_all_from_table(&conn, "LOGS").expect("Should read rows");
+        assert_eq!(stored[1].values, rows[1]);
+
+        let bad_rows = vec![vec![ColumnKind::INTEGER(3)]];
+        assert!(insert_rows_with(&mut conn, DataTable::Logs, &bad_rows).is_err());
+        assert_eq!(query_all_from_table(&conn, "LOGS").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn should_parse_values_by_affinity() {
+        let column = |name: &str, decl_type: &str| TableColumn {
+            name: name.to_owned(),
+            decl_type: decl_type.to_owned(),
+            ..Default::default()
+        };
+
+        assert_eq!(column("a", "varchar(255)").get_affinity(), Affinity::TEXT);
+        assert_eq!(column("a", "float").get_affinity(), Affinity::REAL);
+        assert_eq!(column("a", "BIGINT").get_affinity(), Affinity::INTEGER);
+        assert_eq!(column("a", "").get_affinity(), Affinity::BLOB);
+        assert_eq!(
+            column("a", "DECIMAL(10,5)").get_affinity(),
+            Affinity::NUMERIC
+        );
+
+        assert_eq!(
+            column("count", "int").parse_value("42").unwrap(),
+            ColumnKind::INTEGER(42)
+        );
+        assert!(column("count", "int").parse_value("4.2").is_err());
+        assert_eq!(
+            column("price", "decimal").parse_value("4.2").unwrap(),
+            ColumnKind::FLOAT(4.2)
+        );
+        assert_eq!(
+            column("price", "float").parse_value(" ").unwrap(),
+            ColumnKind::NULL
+        );
+        assert_eq!(
+            column("note", "text").parse_value("").unwrap(),
+            ColumnKind::STRING("".to_owned())
+        );
+    }
+
+    #[test]
+    fn should_update_and_insert_single_rows() {
+        let conn = setup_test_db();
+        let rows = query_all_from_table(&conn, "DEVICES").unwrap();
+        let mut new_values = rows[1].values.clone();
+        new_values[1] = ColumnKind::STRING("shed".to_owned());
+
+        let updated = update_row(&conn, &rows[1], &new_values, "DEVICES").unwrap();
+        assert_eq!(updated, 1);
+
+        let columns = rows[0].columns.clone();
+        let values = vec![
+            ColumnKind::INTEGER(9),
+            ColumnKind::STRING("porch".to_owned()),
+            ColumnKind::NULL,
+            ColumnKind::NULL,
+        ];
+        let inserted = insert_row(&conn, &columns, &values, "DEVICES").unwrap();
+        assert_eq!(inserted, 1);
+
+        let rows = query_all_from_table(&conn, "DEVICES").unwrap();
+        assert_eq!(
+            rows[1].get("name"),
+            Some(&ColumnKind::STRING("shed".to_owned()))
+        );
+        assert_eq!(rows.last().unwrap().values, values);
+    }
+
+    #[test]
+    fn should_target_single_duplicate_by_rowid() {
+        let conn = setup_test_db();
+        conn.execute_batch(
+            "INSERT INTO DEVICES VALUES (3, 'attic', 50.0, NULL), (3, 'attic', 50.0, NULL);",
+        )
+        .expect("Should insert duplicates");
+
+        let rows =
+            query_page_from_table(&conn, "DEVICES", &TableFilter::default(), None, 0, 10).unwrap();
+        assert_eq!(rows[0].rowid, Some(4));
+        assert_eq!(count_matching(&conn, &rows[0], "DEVICES").unwrap(), 1);
+
+        let mut new_values = rows[0].values.clone();
+        new_values[1] = ColumnKind::STRING("loft".to_owned());
+        assert_eq!(
+            update_row(&conn, &rows[0], &new_values, "DEVICES").unwrap(),
+            1
+        );
+        assert_eq!(delete_row(&conn, &rows[1], "DEVICES").unwrap(), 1);
+
+        let names: Vec<ColumnKind> = query_all_from_table(&conn, "DEVICES")
+            .unwrap()
+            .iter()
+            .filter_map(|row| row.get("name").cloned())
+            .collect();
+        assert_eq!(names.last(), Some(&ColumnKind::STRING("loft".to_owned())));
+        assert_eq!(names.len(), 3);
+    }
+
+    #[test]
+    fn should_find_duplicates_by_timestamp_and_topic() {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        migrate(&mut conn, MIGRATIONS).expect("Should migrate");
+        conn.execute_batch(
+            "INSERT INTO LOGS VALUES (1, '/door', 'open'), (1, '/door', 'open'), (2, '/door', 'x');",
+        )
+        .expect("Should insert duplicates");
+
+        let rows = query_rows_at(&conn, "LOGS", 1, "/door").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].rowid, Some(2));
+
+        assert_eq!(delete_rows(&mut conn, &rows[1..], "LOGS").unwrap().len(), 1);
+        assert_eq!(query_rows_at(&conn, "LOGS", 1, "/door").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn should_target_rows_by_primary_key_without_rowid() {
+        let conn = setup_test_db();
+        conn.execute_batch(
+            "
+            CREATE TABLE SETTINGS (key text PRIMARY KEY, value text) WITHOUT ROWID;
+            INSERT INTO SETTINGS VALUES ('a', 'same'), ('b', 'same');
+            ",
+        )
+        .expect("Should create table");
+
+        let rows =
+            query_page_from_table(&conn, "SETTINGS", &TableFilter::default(), None, 0, 10).unwrap();
+        assert_eq!(rows[0].rowid, None);
+        assert!(rows[0].columns[0].primary_key);
+        assert_eq!(count_matching(&conn, &rows[0], "SETTINGS").unwrap(), 1);
+
+        let row = DBRow {
+            values: vec![ColumnKind::STRING("c".to_owned()), ColumnKind::NULL],
+            ..rows[0].clone()
+        };
+        assert_eq!(count_matching(&conn, &row, "SETTINGS").unwrap(), 0);
+    }
+
+    #[test]
+    fn should_delete_rows_in_one_transaction() {
+        let mut conn = setup_test_db();
+        let rows =
+            query_page_from_table(&conn, "DEVICES", &TableFilter::default(), None, 0, 10).unwrap();
+
+        let deleted = delete_rows(&mut conn, &rows, "DEVICES").unwrap();
+        assert_eq!(deleted.len(), 2);
+        assert!(query_all_from_table(&conn, "DEVICES").unwrap().is_empty());
+
+        // Rows are newest first, so the kitchen is deleted second and the garage rolled back.
+        conn.execute_batch(
+            "
+            CREATE TRIGGER KEEP_KITCHEN BEFORE DELETE ON DEVICES WHEN old.id = 1
+            BEGIN SELECT RAISE(ABORT, 'kept'); END;
+            ",
+        )
+        .expect("Should create trigger");
+        restore_rows(&mut conn, &deleted, "DEVICES").unwrap();
+        assert!(delete_rows(&mut conn, &rows, "DEVICES").is_err());
+        assert_eq!(query_all_from_table(&conn, "DEVICES").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn should_delete_matching_rows_and_restore_them() {
+        let mut conn = setup_test_db();
+        let before =
+            query_page_from_table(&conn, "DEVICES", &TableFilter::default(), None, 0, 10).unwrap();
+        let filter = TableFilter {
+            text: "kitchen".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(count_in_table(&conn, "DEVICES", &filter).unwrap(), 1);
+
+        let (count, deleted) = delete_matching(&mut conn, "DEVICES", &filter, 10).unwrap();
+        let deleted = deleted.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(deleted[0].rowid, Some(1));
+        assert_eq!(count_in_table(&conn, "DEVICES", &filter).unwrap(), 0);
+
+        assert_eq!(restore_rows(&mut conn, &deleted, "DEVICES").unwrap(), 1);
+        let after =
+            query_page_from_table(&conn, "DEVICES", &TableFilter::default(), None, 0, 10).unwrap();
+        let ids =
+            |rows: &[DBRow]| -> Vec<Option<i64>> { rows.iter().map(|row| row.rowid).collect() };
+        assert_eq!(ids(&after), ids(&before));
+        assert_eq!(after[1].values, before[1].values);
+    }
+
+    #[test]
+    fn should_skip_undo_snapshot_of_big_deletes() {
+        let mut conn = setup_test_db();
+        let (count, deleted) =
+            delete_matching(&mut conn, "DEVICES", &TableFilter::default(), 1).unwrap();
+        assert_eq!(count, 2);
+        assert!(deleted.is_none());
+        assert!(query_all_from_table(&conn, "DEVICES").unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_restore_rows_whose_rowid_was_taken() {
+        let mut conn = setup_test_db();
+        let filter = TableFilter {
+            text: "garage".to_owned(),
+            ..Default::default()
+        };
+        let deleted = delete_matching(&mut conn, "DEVICES", &filter, 10)
+            .unwrap()
+            .1
+            .unwrap();
+        assert_eq!(deleted[0].rowid, Some(2));
+
+        // New rows get the highest rowid plus one, which is the freed one here.
+        conn.execute_batch("INSERT INTO DEVICES VALUES (3, 'shed', NULL, NULL);")
+            .expect("Should insert");
+        assert_eq!(restore_rows(&mut conn, &deleted, "DEVICES").unwrap(), 1);
+
+        let rows =
+            query_page_from_table(&conn, "DEVICES", &TableFilter::default(), None, 0, 10).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].rowid, Some(3));
+        assert_eq!(
+            rows[0].get("name"),
+            Some(&ColumnKind::STRING("garage".to_owned()))
+        );
+        assert_eq!(
+            rows[1].get("name"),
+            Some(&ColumnKind::STRING("shed".to_owned()))
+        );
+    }
+
+    #[test]
+    fn should_add_indexes_to_existing_tables() {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        conn.execute_batch(
+            "
+            CREATE TABLE MEASUREMENTS (timestamp int, topic varchar(255), value float);
+            INSERT INTO MEASUREMENTS VALUES (1, '/home/kitchen/temp', 20.0);
+            ",
+        )
+        .expect("Should create a table without indexes");
+
+        migrate(&mut conn, MIGRATIONS).expect("Should add indexes");
+        assert_eq!(
+            count_in_table(&conn, "MEASUREMENTS", &TableFilter::default()).unwrap(),
+            1
+        );
+
+        let plan = |topic: &str| -> String {
+            let filter = TableFilter {
+                topic: topic.to_owned(),
+                ..Default::default()
+            };
+            let (sql, params, _) = build_select(&conn, "MEASUREMENTS", &filter, true, None, None)
+                .expect("Should build select");
+            let mut statement = conn
+                .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+                .unwrap();
+            statement
+                .query_map(params_from_iter(params.iter()), |row| {
+                    row.get::<usize, String>(3)
+                })
+                .unwrap()
+                .collect::<Result<Vec<String>, rusqlite::Error>>()
+                .unwrap()
+                .join("\n")
+        };
+        assert!(plan("/home/kitchen/temp").contains("MEASUREMENTS_TOPIC_TIMESTAMP"));
+        assert!(plan("/home/+/temp").contains("MEASUREMENTS_TOPIC_TIMESTAMP"));
+    }
+
+    #[test]
+    fn should_fail_for_missing_table() {
+        let conn = setup_test_db();
+        assert!(query_all_from_table(&conn, "NOPE").is_err());
+    }
 }
@@ -0,0 +1,267 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+use crate::db_interactions::{
+    ColumnKind, DBRow, TableColumn, TableFilter, for_each_row_in_table, get_table_columns,
+    quote_identifier,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Json,
+    /// INSERT statements that can be run with sqlite3
+    Sql,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Sql];
+
+    pub fn get_title(&self) -> &str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON Lines",
+            ExportFormat::Sql => "SQL INSERT statements",
+        }
+    }
+
+    pub fn get_extension(&self) -> &str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "jsonl",
+            ExportFormat::Sql => "sql",
+        }
+    }
+}
+
+/// Writes every row of `table_name` matching `filter` to `path`, oldest first. Values are
+/// written as stored, not as the table screen formats them. Returns the number of rows.
+/// The rows go to a temporary file first, so a failed export leaves `path` as it was.
+pub fn export_table(
+    table_name: &str,
+    filter: &TableFilter,
+    format: ExportFormat,
+    path: &str,
+) -> Result<usize> {
+    let temp_path = format!("{}.part", path);
+    match write_export(table_name, filter, format, &temp_path) {
+        Ok(row_count) => {
+            fs::rename(&temp_path, path)?;
+            Ok(row_count)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_export(
+    table_name: &str,
+    filter: &TableFilter,
+    format: ExportFormat,
+    path: &str,
+) -> Result<usize> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_header(&mut writer, &get_table_columns(table_name)?, format)?;
+
+    let row_count = for_each_row_in_table(table_name, filter, |row| {
+        write_row(&mut writer, table_name, row, format)
+    })?;
+
+    writer.flush()?;
+    Ok(row_count)
+}
+
+/// Column names for CSV, written even when no row matches. Other formats need none.
+fn write_header(
+    writer: &mut impl Write,
+    columns: &[TableColumn],
+    format: ExportFormat,
+) -> Result<()> {
+    if format == ExportFormat::Csv {
+        let names: Vec<String> = columns
+            .iter()
+            .map(|column| csv_field(&column.name))
+            .collect();
+        writeln!(writer, "{}", names.join(","))?;
+    }
+    Ok(())
+}
+
+fn write_row(
+    writer: &mut impl Write,
+    table_name: &str,
+    row: &DBRow,
+    format: ExportFormat,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let fields: Vec<String> = row.values.iter().map(csv_value).collect();
+            writeln!(writer, "{}", fields.join(","))?;
+        }
+        ExportFormat::Json => {
+            let object: Map<String, Value> = row
+                .columns
+                .iter()
+                .zip(row.values.iter())
+                .map(|(column, value)| (column.name.to_owned(), json_value(value)))
+                .collect();
+            writeln!(writer, "{}", Value::Object(object))?;
+        }
+        ExportFormat::Sql => {
+            let names: Vec<String> = row
+                .columns
+                .iter()
+                .map(|column| quote_identifier(&column.name))
+                .collect();
+            let values: Vec<String> = row.values.iter().map(sql_literal).collect();
+            writeln!(
+                writer,
+                "INSERT INTO {} ({}) VALUES ({});",
+                quote_identifier(table_name),
+                names.join(", "),
+                values.join(", ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn csv_value(value: &ColumnKind) -> String {
+    match value {
+        ColumnKind::NULL => "".to_owned(),
+        ColumnKind::INTEGER(val) => val.to_string(),
+        ColumnKind::FLOAT(val) => val.to_string(),
+        ColumnKind::STRING(val) => csv_field(val),
+        ColumnKind::BLOB(val) => hex_string(val),
+    }
+}
+
+fn json_value(value: &ColumnKind) -> Value {
+    match value {
+        ColumnKind::NULL => Value::Null,
+        ColumnKind::INTEGER(val) => Value::from(*val),
+        ColumnKind::FLOAT(val) => Value::from(*val),
+        ColumnKind::STRING(val) => Value::from(val.as_str()),
+        ColumnKind::BLOB(val) => Value::from(hex_string(val)),
+    }
+}
+
+fn sql_literal(value: &ColumnKind) -> String {
+    match value {
+        ColumnKind::NULL => "NULL".to_owned(),
+        ColumnKind::INTEGER(val) => val.to_string(),
+        // SQL has no literal for these, 9e999 reads back as infinity.
+        ColumnKind::FLOAT(val) if val.is_nan() => "NULL".to_owned(),
+        ColumnKind::FLOAT(val) if val.is_infinite() => match val.is_sign_positive() {
+            true => "9e999".to_owned(),
+            false => "-9e999".to_owned(),
+        },
+        // Debug keeps a decimal point so whole numbers stay REAL when read back.
+        ColumnKind::FLOAT(val) => format!("{:?}", val),
+        ColumnKind::STRING(val) => format!("'{}'", val.replace('\'', "''")),
+        ColumnKind::BLOB(val) => format!("X'{}'", hex_string(val)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn test_row() -> DBRow {
+        let columns = ["timestamp", "topic", "value", "raw"]
+            .iter()
+            .map(|name| TableColumn {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        DBRow {
+            columns: Arc::new(columns),
+            values: vec![
+                ColumnKind::INTEGER(1700000000),
+                ColumnKind::STRING("/home/\"kitchen\", it's".to_owned()),
+                ColumnKind::FLOAT(21.0),
+                ColumnKind::BLOB(vec![0, 255]),
+            ],
+            rowid: None,
+        }
+    }
+
+    fn written(format: ExportFormat) -> String {
+        let mut out = vec![];
+        write_row(&mut out, "LOGS", &test_row(), format).expect("Should write row");
+        String::from_utf8(out).expect("Should be utf8")
+    }
+
+    #[test]
+    fn should_write_csv_rows() {
+        assert_eq!(
+            written(ExportFormat::Csv),
+            "1700000000,\"/home/\"\"kitchen\"\", it's\",21,00ff\n"
+        );
+    }
+
+    #[test]
+    fn should_write_csv_header_without_rows() {
+        let mut out = vec![];
+        write_header(&mut out, &test_row().columns, ExportFormat::Csv).expect("Should write");
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,topic,value,raw\n"
+        );
+
+        let mut out = vec![];
+        write_header(&mut out, &test_row().columns, ExportFormat::Json).expect("Should write");
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn should_write_non_finite_floats_as_valid_sql() {
+        assert_eq!(sql_literal(&ColumnKind::FLOAT(f64::NAN)), "NULL");
+        assert_eq!(sql_literal(&ColumnKind::FLOAT(f64::INFINITY)), "9e999");
+        assert_eq!(sql_literal(&ColumnKind::FLOAT(f64::NEG_INFINITY)), "-9e999");
+    }
+
+    #[test]
+    fn should_write_json_rows() {
+        let line = written(ExportFormat::Json);
+        let parsed: Value = serde_json::from_str(&line).expect("Should be valid json");
+        assert_eq!(parsed["timestamp"], 1700000000);
+        assert_eq!(parsed["topic"], "/home/\"kitchen\", it's");
+        assert_eq!(parsed["value"], 21.0);
+        assert_eq!(parsed["raw"], "00ff");
+    }
+
+    #[test]
+    fn should_write_sql_rows() {
+        assert_eq!(
+            written(ExportFormat::Sql),
+            "INSERT INTO \"LOGS\" (\"timestamp\", \"topic\", \"value\", \"raw\") \
+             VALUES (1700000000, '/home/\"kitchen\", it''s', 21.0, X'00ff');\n"
+        );
+    }
+}
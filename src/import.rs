@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Error},
+    path::Path,
+};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::db_interactions::{ColumnKind, DBRow, DataTable, insert_rows};
+
+/// Rows written per transaction.
+const BATCH_SIZE: usize = 1000;
+/// Errors kept for the summary, the rest are only counted.
+const MAX_REPORTED_ERRORS: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    /// Comma separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+impl ImportFormat {
+    pub const ALL: [ImportFormat; 2] = [ImportFormat::Csv, ImportFormat::Json];
+
+    pub fn get_title(&self) -> &str {
+        match self {
+            ImportFormat::Csv => "CSV",
+            ImportFormat::Json => "JSON Lines",
+        }
+    }
+
+    /// Guesses the format from the extension of `path`.
+    pub fn from_path(path: &str) -> Option<ImportFormat> {
+        let extension = Path::new(path)
+            .extension()?
+            .to_string_lossy()
+            .to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "json" | "jsonl" | "ndjson" => Some(ImportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    pub path: String,
+    pub format: ImportFormat,
+    pub table: DataTable,
+    /// Field of the file to read each column from. Columns that are not mapped are read from
+    /// a field with the same name.
+    pub mapping: HashMap<String, String>,
+}
+
+impl ImportOptions {
+    fn get_source_field<'a>(&'a self, column: &'a str) -> &'a str {
+        self.mapping
+            .get(column)
+            .map(|field| field.as_str())
+            .unwrap_or(column)
+    }
+}
+
+#[derive(Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+    /// The first few reasons rows were skipped, with their line numbers.
+    pub errors: Vec<String>,
+    /// Parsed rows of a dry run.
+    pub preview: Vec<DBRow>,
+    pub dry_run: bool,
+}
+
+impl ImportSummary {
+    pub fn describe(&self) -> String {
+        let mut lines = vec![if self.dry_run {
+            format!(
+                "{} rows parsed, {} rows skipped",
+                self.preview.len(),
+                self.skipped
+            )
+        } else {
+            format!(
+                "{} rows imported, {} rows skipped",
+                self.imported, self.skipped
+            )
+        }];
+        lines.extend(self.errors.iter().cloned());
+        if self.skipped > self.errors.len() {
+            lines.push(format!("... and {} more", self.skipped - self.errors.len()));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Parses "column=field" pairs into an `ImportOptions::mapping`.
+pub fn parse_mapping(pairs: &[String]) -> Result<HashMap<String, String>> {
+    pairs
+        .iter()
+        .map(|pair| {
+            let (column, field) = pair
+                .split_once('=')
+                .ok_or(Error::other(format!("Expected COLUMN=FIELD, got {}", pair)))?;
+            if !DataTable::COLUMN_NAMES.contains(&column) {
+                return Err(Error::other(format!("Unknown column: {}", column)).into());
+            }
+            Ok((column.to_owned(), field.to_owned()))
+        })
+        .collect()
+}
+
+/// Parses the first `row_count` records of the file without writing anything.
+pub fn preview_import(options: &ImportOptions, row_count: usize) -> Result<ImportSummary> {
+    let reader = BufReader::new(File::open(&options.path)?);
+    import_rows(reader, options, Some(row_count), |_| Ok(0))
+}
+
+/// Imports every valid record of the file, one transaction per batch. Invalid records are
+/// skipped and reported in the summary.
+pub fn run_import(options: &ImportOptions) -> Result<ImportSummary> {
+    let reader = BufReader::new(File::open(&options.path)?);
+    import_rows(reader, options, None, |rows| {
+        insert_rows(options.table, rows)
+    })
+}
+
+fn import_rows(
+    reader: impl BufRead,
+    options: &ImportOptions,
+    preview: Option<usize>,
+    mut insert: impl FnMut(&[Vec<ColumnKind>]) -> Result<usize>,
+) -> Result<ImportSummary> {
+    let mut summary = ImportSummary {
+        dry_run: preview.is_some(),
+        ..Default::default()
+    };
+    let mut batch: Vec<Vec<ColumnKind>> = vec![];
+    let columns = options.table.get_columns();
+
+    read_records(reader, options.format, |line_no, record| {
+        match record.and_then(|record| map_record(options, &record)) {
+            Ok(values) if preview.is_some() => summary.preview.push(DBRow {
+                columns: columns.clone(),
+                values,
+                rowid: None,
+            }),
+            Ok(values) => {
+                batch.push(values);
+                if batch.len() >= BATCH_SIZE {
+                    summary.imported += insert(&batch)?;
+                    batch.clear();
+                }
+            }
+            Err(e) => {
+                summary.skipped += 1;
+                if summary.errors.len() < MAX_REPORTED_ERRORS {
+                    summary.errors.push(format!("line {}: {}", line_no, e));
+                }
+            }
+        }
+
+        Ok(preview.is_none_or(|row_count| summary.preview.len() + summary.skipped < row_count))
+    })?;
+
+    if !batch.is_empty() {
+        summary.imported += insert(&batch)?;
+    }
+
+    Ok(summary)
+}
+
+fn map_record(
+    options: &ImportOptions,
+    record: &HashMap<String, String>,
+) -> Result<Vec<ColumnKind>> {
+    DataTable::COLUMN_NAMES
+        .iter()
+        .map(|column| {
+            let field = options.get_source_field(column);
+            let raw = record
+                .get(field)
+                .ok_or(Error::other(format!("missing field {}", field)))?;
+            options.table.parse_value(column, raw)
+        })
+        .collect()
+}
+
+/// Calls `on_record` with the line number and fields of every record until it returns false.
+fn read_records(
+    reader: impl BufRead,
+    format: ImportFormat,
+    mut on_record: impl FnMut(usize, Result<HashMap<String, String>>) -> Result<bool>,
+) -> Result<()> {
+    let mut lines = reader.lines().enumerate();
+    let mut header: Option<Vec<String>> = None;
+
+    while let Some((idx, line)) = lines.next() {
+        let line_no = idx + 1;
+        let mut line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = match format {
+            ImportFormat::Csv => {
+                // Quoted fields may span lines, keep reading until the quotes are balanced.
+                let mut fields = split_csv_record(&line);
+                while fields.is_none() {
+                    let Some((_, next_line)) = lines.next() else {
+                        return Err(
+                            Error::other(format!("line {}: unclosed quote", line_no)).into()
+                        );
+                    };
+                    line.push('\n');
+                    line.push_str(&next_line?);
+                    fields = split_csv_record(&line);
+                }
+                let fields = fields.unwrap_or_default();
+
+                let Some(header) = &header else {
+                    header = Some(fields);
+                    continue;
+                };
+                Ok(header.iter().cloned().zip(fields).collect())
+            }
+            ImportFormat::Json => parse_json_record(&line),
+        };
+
+        if !on_record(line_no, record)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits one CSV record into fields. Returns `None` when a quoted field is still open at the
+/// end of the text.
+fn split_csv_record(record: &str) -> Option<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = record.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        match (char, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (char, _) => field.push(char),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+fn parse_json_record(line: &str) -> Result<HashMap<String, String>> {
+    let Value::Object(object) = serde_json::from_str::<Value>(line)? else {
+        return Err(Error::other("expected a JSON object").into());
+    };
+
+    Ok(object
+        .into_iter()
+        .map(|(key, value)| {
+            let raw = match value {
+                Value::String(val) => val,
+                Value::Null => "".to_owned(),
+                other => other.to_string(),
+            };
+            (key, raw)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(format: ImportFormat, table: DataTable) -> ImportOptions {
+        ImportOptions {
+            path: "".to_owned(),
+            format,
+            table,
+            mapping: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn should_split_quoted_csv_fields() {
+        assert_eq!(
+            split_csv_record("1,\"/a,b\",\"say \"\"hi\"\"\""),
+            Some(vec![
+                "1".to_owned(),
+                "/a,b".to_owned(),
+                "say \"hi\"".to_owned()
+            ])
+        );
+        assert_eq!(split_csv_record("1,\"open"), None);
+    }
+
+    #[test]
+    fn should_import_csv_in_batches_with_mapping() {
+        let mut csv = "time,sensor,reading\n".to_owned();
+        (0..BATCH_SIZE + 5).for_each(|idx| csv.push_str(&format!("{},/temp,{}.5\n", idx, idx)));
+        csv.push_str("9,/temp,warm\n");
+        let mut options = options(ImportFormat::Csv, DataTable::Measurements);
+        options.mapping = parse_mapping(&[
+            "timestamp=time".to_owned(),
+            "topic=sensor".to_owned(),
+            "value=reading".to_owned(),
+        ])
+        .unwrap();
+
+        let mut batches = vec![];
+        let summary = import_rows(csv.as_bytes(), &options, None, |rows| {
+            batches.push(rows.to_vec());
+            Ok(rows.len())
+        })
+        .expect("Should import");
+
+        assert_eq!(summary.imported, BATCH_SIZE + 5);
+        assert_eq!(summary.skipped, 1);
+        assert!(summary.errors[0].starts_with(&format!("line {}:", BATCH_SIZE + 7)));
+        assert_eq!(batches.len(), 2);
+        assert_eq!(
+            batches[1][0],
+            vec![
+                ColumnKind::INTEGER(BATCH_SIZE as i64),
+                ColumnKind::STRING("/temp".to_owned()),
+                ColumnKind::FLOAT(BATCH_SIZE as f64 + 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_preview_json_lines_without_inserting() {
+        let json = "{\"timestamp\": 1, \"topic\": \"/door\", \"value\": \"open\"}\n\
+                    {\"timestamp\": 2, \"topic\": \"/door\"}\n\
+                    {\"timestamp\": 3, \"topic\": \"/door\", \"value\": 1}\n\
+                    {\"timestamp\": 4, \"topic\": \"/door\", \"value\": \"closed\"}\n";
+        let options = options(ImportFormat::Json, DataTable::Logs);
+
+        let summary = import_rows(json.as_bytes(), &options, Some(3), |_| {
+            panic!("Preview should not insert rows")
+        })
+        .expect("Should preview");
+
+        assert_eq!(summary.preview.len(), 2);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(
+            summary.errors,
+            vec!["line 2: missing field value".to_owned()]
+        );
+        assert_eq!(
+            summary.preview[1].get("value"),
+            Some(&ColumnKind::STRING("1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn should_reject_unknown_mapping_columns() {
+        assert!(parse_mapping(&["unit=u".to_owned()]).is_err());
+        assert!(parse_mapping(&["value".to_owned()]).is_err());
+    }
+}
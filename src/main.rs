@@ -1,4 +1,8 @@
-use mqttui::{db_interactions, main_menu, siv_utils};
+use mqttui::{
+    cli::run_command,
+    cli_args::{ARGS, Command},
+    db_interactions, main_menu, siv_utils,
+};
 
 use anyhow::Result;
 use cursive::{Cursive, CursiveExt};
@@ -7,7 +11,18 @@ use main_menu::draw_main_menu;
 use siv_utils::{check_config, quit};
 
 fn main() -> Result<()> {
-    setup_db()?;
+    // Migrating first would leave a dry run nothing to report.
+    if !matches!(ARGS.command, Some(Command::Migrate { .. })) {
+        setup_db()?;
+    }
+
+    match &ARGS.command {
+        None | Some(Command::Tui) => run_tui(),
+        Some(command) => run_command(command),
+    }
+}
+
+fn run_tui() -> Result<()> {
     let mut siv = Cursive::new();
 
     check_config(&mut siv);
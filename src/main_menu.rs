@@ -3,7 +3,13 @@ use cursive::{
     views::{Button, Dialog, DummyView, LinearLayout},
 };
 
-use crate::{tui_config::draw_config, tui_logs::draw_logs, tui_tables::draw_db_explorer};
+use crate::{
+    tui_config::draw_config,
+    tui_db_health::{draw_db_health, refresh_db_health},
+    tui_logs::draw_logs,
+    tui_query::draw_query,
+    tui_tables::draw_db_explorer,
+};
 
 pub fn draw_main_menu(s: &mut Cursive) {
     let main_menu_id = s.add_screen();
@@ -18,10 +24,18 @@ pub fn draw_main_menu(s: &mut Cursive) {
     s.set_screen(logs_screen_id);
     draw_logs(s, main_menu_id);
 
+    let query_screen_id = s.add_screen();
+    s.set_screen(query_screen_id);
+    draw_query(s, main_menu_id);
+
     let config_screen_id = s.add_screen();
     s.set_screen(config_screen_id);
     draw_config(s, main_menu_id);
 
+    let database_screen_id = s.add_screen();
+    s.set_screen(database_screen_id);
+    draw_db_health(s, main_menu_id);
+
     // Temporary global cb to get back to main menu when pressing B.
     s.add_global_callback('b', move |s| {
         s.set_screen(main_menu_id);
@@ -38,6 +52,13 @@ pub fn draw_main_menu(s: &mut Cursive) {
                 .child(Button::new("LOGS", move |s| {
                     s.set_screen(logs_screen_id);
                 }))
+                .child(Button::new("QUERY", move |s| {
+                    s.set_screen(query_screen_id);
+                }))
+                .child(Button::new("DATABASE", move |s| {
+                    s.set_screen(database_screen_id);
+                    refresh_db_health(s);
+                }))
                 .child(Button::new("CONFIGURE", move |s| {
                     s.set_screen(config_screen_id);
                 }))
@@ -59,6 +80,13 @@ pub fn draw_main_menu(s: &mut Cursive) {
             .leaf("Logs", move |s| {
                 s.set_screen(logs_screen_id);
             })
+            .leaf("Query", move |s| {
+                s.set_screen(query_screen_id);
+            })
+            .leaf("Database", move |s| {
+                s.set_screen(database_screen_id);
+                refresh_db_health(s);
+            })
             .leaf("Main menu", move |s| {
                 s.set_screen(main_menu_id);
             }),
@@ -0,0 +1,229 @@
+use std::{io::Error, path::Path, time::Duration};
+
+use anyhow::Result;
+use rusqlite::{Connection, OpenFlags, TransactionBehavior};
+
+use crate::cli_args::ARGS;
+
+/// How long to wait for the SubStore service to finish a write before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A step of the schema, applied once and recorded in `PRAGMA user_version`.
+///
+/// The SubStore service inserts `timestamp, topic, value` rows into MEASUREMENTS and LOGS
+/// and knows nothing about these versions, so a migration must keep those inserts working.
+/// Applied migrations are never edited, changes go into a new one at the end.
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every migration in the order they are applied, numbered from 1 without gaps.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create MEASUREMENTS and LOGS",
+        // Databases from before versioning already have the tables.
+        sql: "
+        CREATE TABLE if not exists MEASUREMENTS (
+                timestamp int,
+                topic varchar(255),
+                value float
+        );
+        CREATE TABLE if not exists LOGS (
+                timestamp int,
+                topic varchar(255),
+                value varchar(255)
+        );
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "Index topic and timestamp of MEASUREMENTS and LOGS",
+        sql: INDEXES_SQL,
+    },
+];
+
+/// Builds migration 2 and `PROTECTED_INDEXES` from one list, so they can not disagree.
+macro_rules! migration_indexes {
+    ($(($name:literal, $on:literal)),* $(,)?) => {
+        /// Indexes of migration 2. Nothing creates them again once dropped.
+        pub const PROTECTED_INDEXES: &[&str] = &[$($name),*];
+        const INDEXES_SQL: &str =
+            concat!($("CREATE INDEX if not exists ", $name, " ON ", $on, ";\n"),*);
+    };
+}
+
+migration_indexes!(
+    (
+        "MEASUREMENTS_TOPIC_TIMESTAMP",
+        "MEASUREMENTS (topic, timestamp)"
+    ),
+    ("MEASUREMENTS_TIMESTAMP", "MEASUREMENTS (timestamp)"),
+    ("LOGS_TOPIC_TIMESTAMP", "LOGS (topic, timestamp)"),
+    ("LOGS_TIMESTAMP", "LOGS (timestamp)"),
+);
+
+/// Version a database has once every migration ran.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Version of the database at `ARGS.db_path` and the migrations it is missing, without
+/// creating or changing the file.
+pub fn get_pending_migrations() -> Result<(i64, Vec<&'static Migration>)> {
+    if !Path::new(&ARGS.db_path).exists() {
+        return Ok((0, MIGRATIONS.iter().collect()));
+    }
+    let conn = Connection::open_with_flags(&ARGS.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let version = read_version(&conn)?;
+    Ok((version, pending(MIGRATIONS, version)?))
+}
+
+/// Brings the database at `ARGS.db_path` up to the latest version. Returns the migrations
+/// that ran.
+pub fn run_migrations() -> Result<Vec<&'static Migration>> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    migrate(&mut conn, MIGRATIONS)
+}
+
+pub(crate) fn migrate<'a>(
+    conn: &mut Connection,
+    migrations: &'a [Migration],
+) -> Result<Vec<&'a Migration>> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+
+    let mut applied = vec![];
+    loop {
+        // Taking the write lock before reading the version keeps two processes starting
+        // at the same time from both applying a migration.
+        let transaction = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let Some(migration) = pending(migrations, read_version(&transaction)?)?
+            .first()
+            .copied()
+        else {
+            return Ok(applied);
+        };
+        transaction.execute_batch(migration.sql).map_err(|e| {
+            Error::other(format!(
+                "Migration {} ({}) failed, the database was left at version {}: {}",
+                migration.version,
+                migration.description,
+                migration.version - 1,
+                e
+            ))
+        })?;
+        transaction.pragma_update(None, "user_version", migration.version)?;
+        transaction.commit()?;
+        applied.push(migration);
+    }
+}
+
+fn read_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA user_version;", [], |row| row.get(0))?)
+}
+
+fn pending(migrations: &[Migration], version: i64) -> Result<Vec<&Migration>> {
+    let latest = migrations.last().map_or(0, |migration| migration.version);
+    if version > latest {
+        return Err(Error::other(format!(
+            "The database is at version {}, but this version of mqttui only knows up to {}. \
+             Update mqttui before using this database.",
+            version, latest
+        ))
+        .into());
+    }
+    Ok(migrations
+        .iter()
+        .filter(|migration| migration.version > version)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn object_names(conn: &Connection) -> Vec<String> {
+        let mut statement = conn
+            .prepare("SELECT name FROM sqlite_master ORDER BY name;")
+            .unwrap();
+        statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<String>, rusqlite::Error>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_number_migrations_in_order() {
+        MIGRATIONS
+            .iter()
+            .enumerate()
+            .for_each(|(idx, migration)| assert_eq!(migration.version, idx as i64 + 1));
+    }
+
+    #[test]
+    fn should_migrate_new_databases_once() {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        assert_eq!(
+            migrate(&mut conn, MIGRATIONS).unwrap().len(),
+            MIGRATIONS.len()
+        );
+        assert_eq!(read_version(&conn).unwrap(), latest_version());
+        assert!(object_names(&conn).contains(&"LOGS_TOPIC_TIMESTAMP".to_owned()));
+
+        assert!(migrate(&mut conn, MIGRATIONS).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_upgrade_unversioned_databases_without_data_loss() {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        conn.execute_batch(
+            "
+            CREATE TABLE MEASUREMENTS (timestamp int, topic varchar(255), value float);
+            INSERT INTO MEASUREMENTS VALUES (1, '/temp', 20.0), (2, '/temp', 21.0);
+            ",
+        )
+        .unwrap();
+
+        migrate(&mut conn, MIGRATIONS).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM MEASUREMENTS;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(read_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn should_roll_back_failed_migrations() {
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "Works",
+                sql: "CREATE TABLE A (a int);",
+            },
+            Migration {
+                version: 2,
+                description: "Fails halfway",
+                sql: "CREATE TABLE B (b int); INSERT INTO MISSING VALUES (1);",
+            },
+        ];
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+
+        let error = migrate(&mut conn, &migrations).unwrap_err();
+        assert!(error.to_string().contains("left at version 1"));
+        assert_eq!(read_version(&conn).unwrap(), 1);
+        assert_eq!(object_names(&conn), vec!["A"]);
+    }
+
+    #[test]
+    fn should_refuse_databases_of_newer_versions() {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert!(migrate(&mut conn, MIGRATIONS).is_err());
+        assert!(object_names(&conn).is_empty());
+    }
+}
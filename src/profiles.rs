@@ -0,0 +1,180 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+
+use crate::{
+    broker::{BrokerSettings, write_private},
+    subscriptions::Subscription,
+    utils::config_dir,
+};
+
+/// A named broker to switch to from the LOGS screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub broker: BrokerSettings,
+    /// Subscribed to after switching. The current subscriptions are kept when empty.
+    pub subscriptions: Vec<Subscription>,
+}
+
+/// A `[name]` header followed by the broker settings and a `subscription = ...` line per
+/// subscription, as the config file stores it.
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "[{}]", self.name)?;
+        write!(f, "{}", self.broker)?;
+        for subscription in &self.subscriptions {
+            writeln!(f, "subscription = {}", subscription)?;
+        }
+        Ok(())
+    }
+}
+
+impl Profile {
+    /// One line for the profile list.
+    pub fn describe(&self) -> String {
+        format!("{}: {}", self.name, self.broker.describe())
+    }
+}
+
+/// Profiles of a config file. Settings missing from a profile keep their default, empty
+/// lines and lines starting with `#` are skipped.
+pub fn parse_profiles(content: &str) -> Result<Vec<Profile>> {
+    let mut profiles: Vec<Profile> = vec![];
+    for line in content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            let name = name.trim();
+            if name.is_empty() || profiles.iter().any(|profile| profile.name == name) {
+                return Err(
+                    Error::other(format!("Empty or repeated profile name {}", line)).into(),
+                );
+            }
+            profiles.push(Profile {
+                name: name.to_owned(),
+                broker: BrokerSettings::default(),
+                subscriptions: vec![],
+            });
+            continue;
+        }
+
+        let profile = profiles
+            .last_mut()
+            .ok_or(Error::other(format!("Expected a [name] before {}", line)))?;
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(Error::other(format!("Expected key = value, got {}", line)))?;
+        match key.trim() {
+            "subscription" => profile
+                .subscriptions
+                .push(Subscription::parse(value.trim())?),
+            key => profile
+                .broker
+                .set(key, value.trim())
+                .map_err(|e| Error::other(format!("In profile {}: {}", profile.name, e)))?,
+        }
+    }
+    Ok(profiles)
+}
+
+fn profiles_path() -> PathBuf {
+    config_dir().join("profiles.txt")
+}
+
+/// Saved profiles, none before any were saved.
+pub fn load_profiles() -> Result<Vec<Profile>> {
+    match fs::read_to_string(profiles_path()) {
+        Ok(content) => parse_profiles(&content),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Only the owner can read the file, as it holds passwords.
+pub fn save_profiles(profiles: &[Profile]) -> Result<()> {
+    let content: Vec<String> = profiles.iter().map(|profile| profile.to_string()).collect();
+    write_private(&profiles_path(), &content.join("\n"))
+}
+
+/// Adds `profile`, replacing the one with the same name.
+pub fn upsert_profile(profiles: &mut Vec<Profile>, profile: Profile) {
+    match profiles.iter_mut().find(|saved| saved.name == profile.name) {
+        Some(saved) => *saved = profile,
+        None => profiles.push(profile),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_save_and_parse_profiles() {
+        let mut alarms = Subscription::new("/alarms/#");
+        alarms.color = "red".to_owned();
+        let profiles = vec![
+            Profile {
+                name: "dev".to_owned(),
+                broker: BrokerSettings::default(),
+                subscriptions: vec![],
+            },
+            Profile {
+                name: "site a".to_owned(),
+                broker: BrokerSettings {
+                    host: "10.0.0.2".to_owned(),
+                    username: "site".to_owned(),
+                    password: "secret".to_owned(),
+                    ..Default::default()
+                },
+                subscriptions: vec![Subscription::new("/home/+/temperature"), alarms],
+            },
+        ];
+
+        let content: Vec<String> = profiles.iter().map(|profile| profile.to_string()).collect();
+        assert_eq!(parse_profiles(&content.join("\n")).unwrap(), profiles);
+        assert_eq!(
+            parse_profiles("# mine\n[short]\nhost = broker\n").unwrap()[0].broker,
+            BrokerSettings {
+                host: "broker".to_owned(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn should_reject_malformed_profiles() {
+        assert!(parse_profiles("host = broker\n").is_err());
+        assert!(parse_profiles("[a]\nhost = broker\n[a]\n").is_err());
+        assert!(parse_profiles("[]\n").is_err());
+        assert!(parse_profiles("[a]\ncolour = red\n").is_err());
+        assert!(parse_profiles("[a]\nsubscription = on 1 red\n").is_err());
+    }
+
+    #[test]
+    fn should_replace_profiles_with_the_same_name() {
+        let profile = |name: &str, host: &str| Profile {
+            name: name.to_owned(),
+            broker: BrokerSettings {
+                host: host.to_owned(),
+                ..Default::default()
+            },
+            subscriptions: vec![],
+        };
+        let mut profiles = vec![profile("dev", "localhost")];
+        upsert_profile(&mut profiles, profile("site", "10.0.0.2"));
+        upsert_profile(&mut profiles, profile("dev", "127.0.0.1"));
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].broker.host, "127.0.0.1");
+    }
+}
@@ -0,0 +1,163 @@
+use std::{fs, io::Error, path::PathBuf};
+
+use anyhow::Result;
+use mosquitto_rs::QoS;
+use serde_json::{Value, json};
+
+use crate::{table_layout::fit_to_width, utils::config_dir};
+
+/// Publishes kept for resending, older ones are dropped.
+const HISTORY_LIMIT: usize = 30;
+/// Cells of the payload shown in the history list.
+const PAYLOAD_PREVIEW_WIDTH: usize = 40;
+
+/// A message typed into the PUBLISH form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublishRequest {
+    pub topic: String,
+    pub payload: String,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+impl PublishRequest {
+    /// One line for the history list.
+    pub fn describe(&self) -> String {
+        format!(
+            "QoS {}{} {}: {}",
+            qos_level(self.qos),
+            if self.retain { " retained" } else { "" },
+            self.topic,
+            fit_to_width(&self.payload, PAYLOAD_PREVIEW_WIDTH).trim_end()
+        )
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "topic": self.topic,
+            "payload": self.payload,
+            "qos": qos_level(self.qos),
+            "retain": self.retain,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<PublishRequest> {
+        Some(PublishRequest {
+            topic: value.get("topic")?.as_str()?.to_owned(),
+            payload: value.get("payload")?.as_str()?.to_owned(),
+            qos: qos_from_level(value.get("qos")?.as_u64()?)?,
+            retain: value.get("retain")?.as_bool()?,
+        })
+    }
+}
+
+pub fn qos_level(qos: QoS) -> u64 {
+    match qos {
+        QoS::AtMostOnce => 0,
+        QoS::AtLeastOnce => 1,
+        QoS::ExactlyOnce => 2,
+    }
+}
+
+pub fn qos_from_level(level: u64) -> Option<QoS> {
+    match level {
+        0 => Some(QoS::AtMostOnce),
+        1 => Some(QoS::AtLeastOnce),
+        2 => Some(QoS::ExactlyOnce),
+        _ => None,
+    }
+}
+
+/// Checks `request` can be published, MQTT topics to publish to can not hold wildcards.
+pub fn validate_request(request: &PublishRequest) -> Result<()> {
+    if request.topic.is_empty() {
+        return Err(Error::other("The topic can not be empty.").into());
+    }
+    if request.topic.contains(['+', '#']) {
+        return Err(Error::other("Topics to publish to can not contain + or #.").into());
+    }
+    Ok(())
+}
+
+fn history_path() -> PathBuf {
+    config_dir().join("publish_history.jsonl")
+}
+
+/// Earlier publishes, oldest first. Lines that do not parse are skipped.
+pub fn load_history() -> Vec<PublishRequest> {
+    fs::read_to_string(history_path())
+        .map(|content| parse_history(&content))
+        .unwrap_or_default()
+}
+
+pub fn save_history(history: &[PublishRequest]) -> Result<()> {
+    fs::create_dir_all(config_dir())?;
+    fs::write(history_path(), format_history(history))?;
+    Ok(())
+}
+
+fn parse_history(content: &str) -> Vec<PublishRequest> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|value| PublishRequest::from_json(&value))
+        .collect()
+}
+
+/// One JSON object per line, as payloads can span lines.
+fn format_history(history: &[PublishRequest]) -> String {
+    history
+        .iter()
+        .map(|request| format!("{}\n", request.to_json()))
+        .collect()
+}
+
+/// Moves `request` to the end of `history`, dropping the oldest beyond `HISTORY_LIMIT`.
+pub fn add_to_history(history: &mut Vec<PublishRequest>, request: &PublishRequest) {
+    history.retain(|previous| previous != request);
+    history.push(request.clone());
+    if history.len() > HISTORY_LIMIT {
+        history.drain(..history.len() - HISTORY_LIMIT);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(topic: &str, payload: &str) -> PublishRequest {
+        PublishRequest {
+            topic: topic.to_owned(),
+            payload: payload.to_owned(),
+            qos: QoS::AtLeastOnce,
+            retain: false,
+        }
+    }
+
+    #[test]
+    fn should_keep_history_across_lines_and_restarts() {
+        let mut history = vec![];
+        add_to_history(&mut history, &request("/lamp/set", "{\n  \"on\": true\n}"));
+        add_to_history(&mut history, &request("/fan/set", "off"));
+        add_to_history(&mut history, &request("/lamp/set", "{\n  \"on\": true\n}"));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].topic, "/lamp/set");
+
+        let content = format_history(&history);
+        assert_eq!(content.lines().count(), 2);
+        assert_eq!(parse_history(&content), history);
+        assert_eq!(parse_history("not json\n{\"topic\": 1}\n"), vec![]);
+
+        (0..HISTORY_LIMIT)
+            .for_each(|idx| add_to_history(&mut history, &request("/t", &idx.to_string())));
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history[0].payload, "0");
+    }
+
+    #[test]
+    fn should_reject_wildcard_topics() {
+        assert!(validate_request(&request("/lamp/set", "on")).is_ok());
+        assert!(validate_request(&request("", "on")).is_err());
+        assert!(validate_request(&request("/lamp/+", "on")).is_err());
+    }
+}
@@ -0,0 +1,543 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use chrono::{Local, TimeDelta};
+use rusqlite::{Connection, params_from_iter};
+
+use crate::{
+    cli_args::ARGS,
+    db_interactions::{ColumnKind, DataTable},
+    time_range::{format_duration, parse_duration},
+    utils::config_dir,
+};
+
+/// Written instead of an empty topic prefix, which would not survive splitting on spaces.
+pub const ALL_TOPICS: &str = "*";
+/// Only these rows are averaged, as `stats` and `chart` read them.
+const NUMERIC_VALUE: &str = "typeof(value) IN ('integer', 'real')";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Downsample {
+    /// Width of the buckets raw rows are averaged into.
+    pub bucket: TimeDelta,
+    /// How long the averages are kept.
+    pub keep: TimeDelta,
+}
+
+/// How long rows of a table are kept. Rules with a longer topic prefix take precedence over
+/// the rules of the same table they are more specific than.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetentionRule {
+    pub table: DataTable,
+    /// Topics the rule applies to, empty for every topic.
+    pub topic_prefix: String,
+    /// Rows older than this are dropped, or averaged when `downsample` is set.
+    pub keep_raw: TimeDelta,
+    pub downsample: Option<Downsample>,
+}
+
+impl RetentionRule {
+    /// Reads a rule written as `TABLE PREFIX KEEP_RAW [BUCKET KEEP_AVERAGES]`, for example
+    /// `MEASUREMENTS * 30d 1h 2y` or `LOGS /alarms 7d`. `*` matches every topic.
+    pub fn parse(line: &str) -> Result<RetentionRule> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (table, prefix, keep_raw, downsample) = match fields.as_slice() {
+            [table, prefix, keep_raw] => (table, prefix, keep_raw, None),
+            [table, prefix, keep_raw, bucket, keep] => {
+                (table, prefix, keep_raw, Some((bucket, keep)))
+            }
+            _ => {
+                return Err(Error::other(format!(
+                    "Expected TABLE PREFIX KEEP_RAW [BUCKET KEEP_AVERAGES]: {}",
+                    line
+                ))
+                .into());
+            }
+        };
+
+        let downsample = match downsample {
+            Some((bucket, keep)) => Some(Downsample {
+                bucket: parse_duration(bucket)?,
+                keep: parse_duration(keep)?,
+            }),
+            None => None,
+        };
+        let rule = RetentionRule {
+            table: DataTable::from_table_name(&table.to_uppercase())
+                .ok_or(Error::other(format!("Unknown table: {}", table)))?,
+            topic_prefix: match *prefix {
+                ALL_TOPICS => "".to_owned(),
+                prefix => prefix.to_owned(),
+            },
+            keep_raw: parse_duration(keep_raw)?,
+            downsample,
+        };
+        rule.validate()?;
+
+        Ok(rule)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.keep_raw <= TimeDelta::zero() {
+            return Err(Error::other("Durations have to be positive.").into());
+        }
+        if self.topic_prefix.contains(char::is_whitespace) {
+            return Err(Error::other("Topic prefixes can not contain spaces.").into());
+        }
+        if let Some(downsample) = self.downsample {
+            if self.table != DataTable::Measurements {
+                return Err(Error::other("Only MEASUREMENTS can be averaged.").into());
+            }
+            if downsample.bucket <= TimeDelta::zero() {
+                return Err(Error::other("Durations have to be positive.").into());
+            }
+            if downsample.keep <= self.keep_raw {
+                return Err(Error::other("Averages have to be kept longer than raw rows.").into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        let topics = match self.topic_prefix.as_str() {
+            "" => "all topics".to_owned(),
+            prefix => format!("topics under {}", prefix),
+        };
+        match self.downsample {
+            Some(downsample) => format!(
+                "{}, {}: keep raw rows {}, then {} averages for {}",
+                self.table.get_table_name(),
+                topics,
+                format_duration(self.keep_raw),
+                format_duration(downsample.bucket),
+                format_duration(downsample.keep)
+            ),
+            None => format!(
+                "{}, {}: drop rows older than {}",
+                self.table.get_table_name(),
+                topics,
+                format_duration(self.keep_raw)
+            ),
+        }
+    }
+}
+
+/// Writes the rule the way `RetentionRule::parse` reads it.
+impl Display for RetentionRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = match self.topic_prefix.as_str() {
+            "" => ALL_TOPICS,
+            prefix => prefix,
+        };
+        write!(
+            f,
+            "{} {} {}",
+            self.table.get_table_name(),
+            prefix,
+            format_duration(self.keep_raw)
+        )?;
+        if let Some(downsample) = self.downsample {
+            write!(
+                f,
+                " {} {}",
+                format_duration(downsample.bucket),
+                format_duration(downsample.keep)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn rules_path() -> PathBuf {
+    config_dir().join("retention.txt")
+}
+
+/// Rules are stored one per line. Empty lines and lines starting with `#` are skipped.
+pub fn load_rules() -> Result<Vec<RetentionRule>> {
+    match fs::read_to_string(rules_path()) {
+        Ok(content) => content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(RetentionRule::parse)
+            .collect(),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_rules(rules: &[RetentionRule]) -> Result<()> {
+    let lines: Vec<String> = rules.iter().map(|rule| rule.to_string()).collect();
+    fs::create_dir_all(config_dir())?;
+    fs::write(rules_path(), format!("{}\n", lines.join("\n")))?;
+    Ok(())
+}
+
+/// Rows a rule removed or would remove.
+pub struct RuleEffect {
+    pub rule: RetentionRule,
+    /// Rows older than the rule keeps anything.
+    pub dropped: usize,
+    /// Raw rows replaced by averages.
+    pub averaged: usize,
+    /// Average rows written in their place.
+    pub averages: usize,
+}
+
+impl RuleEffect {
+    pub fn describe(&self) -> String {
+        match self.rule.downsample {
+            Some(_) => format!(
+                "{}\n  {} rows dropped, {} rows averaged into {}",
+                self.rule.describe(),
+                self.dropped,
+                self.averaged,
+                self.averages
+            ),
+            None => format!("{}\n  {} rows dropped", self.rule.describe(), self.dropped),
+        }
+    }
+}
+
+/// Runs `rules` and rolls them back, so the effects are exact without changing anything.
+pub fn preview_retention(rules: &[RetentionRule]) -> Result<Vec<RuleEffect>> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    run_rules(&mut conn, rules, Local::now().timestamp(), false)
+}
+
+/// Runs `rules` in a single transaction.
+pub fn apply_retention(rules: &[RetentionRule]) -> Result<Vec<RuleEffect>> {
+    let mut conn = Connection::open(&ARGS.db_path)?;
+    run_rules(&mut conn, rules, Local::now().timestamp(), true)
+}
+
+fn run_rules(
+    conn: &mut Connection,
+    rules: &[RetentionRule],
+    now: i64,
+    commit: bool,
+) -> Result<Vec<RuleEffect>> {
+    let transaction = conn.transaction()?;
+    let effects = rules
+        .iter()
+        .map(|rule| run_rule(&transaction, rule, rules, now))
+        .collect::<Result<Vec<RuleEffect>>>()?;
+
+    if commit {
+        transaction.commit()?;
+    } else {
+        transaction.rollback()?;
+    }
+
+    Ok(effects)
+}
+
+fn run_rule(
+    conn: &Connection,
+    rule: &RetentionRule,
+    rules: &[RetentionRule],
+    now: i64,
+) -> Result<RuleEffect> {
+    let table = rule.table.get_table_name();
+    let (topics, mut params) = topic_condition(rule, rules);
+    let cutoff_param = params.len() + 1;
+    let bucket_param = params.len() + 2;
+    let mut effect = RuleEffect {
+        rule: rule.clone(),
+        dropped: 0,
+        averaged: 0,
+        averages: 0,
+    };
+
+    let keep = match rule.downsample {
+        Some(downsample) => downsample.keep,
+        None => rule.keep_raw,
+    };
+    params.push(ColumnKind::INTEGER(now - keep.num_seconds()));
+    effect.dropped = conn.execute(
+        &format!(
+            "DELETE FROM {} WHERE timestamp < ?{} AND {};",
+            table, cutoff_param, topics
+        ),
+        params_from_iter(params.iter()),
+    )?;
+
+    let Some(downsample) = rule.downsample else {
+        return Ok(effect);
+    };
+
+    // Only whole buckets are averaged, into a row at the start of the bucket. Buckets that
+    // already hold just that row are skipped, so running a rule twice changes nothing. Text
+    // values are left as they are until they are dropped.
+    let bucket = downsample.bucket.num_seconds();
+    let raw_cutoff = now - rule.keep_raw.num_seconds();
+    params.pop();
+    params.push(ColumnKind::INTEGER((raw_cutoff / bucket) * bucket));
+    params.push(ColumnKind::INTEGER(bucket));
+
+    conn.execute(
+        &format!(
+            "CREATE TEMP TABLE retention_buckets AS \
+             SELECT topic, (timestamp / ?{1}) * ?{1} AS bucket, AVG(value) AS value \
+             FROM {0} WHERE timestamp < ?{2} AND {3} AND {4} \
+             GROUP BY topic, bucket HAVING COUNT(*) > 1 OR MIN(timestamp) != bucket;",
+            table, bucket_param, cutoff_param, topics, NUMERIC_VALUE
+        ),
+        params_from_iter(params.iter()),
+    )?;
+    effect.averaged = conn.execute(
+        &format!(
+            "DELETE FROM {0} WHERE timestamp < ?{2} AND {3} AND {4} AND EXISTS ( \
+             SELECT 1 FROM temp.retention_buckets AS buckets \
+             WHERE buckets.topic IS {0}.topic \
+             AND buckets.bucket = ({0}.timestamp / ?{1}) * ?{1});",
+            table, bucket_param, cutoff_param, topics, NUMERIC_VALUE
+        ),
+        params_from_iter(params.iter()),
+    )?;
+    effect.averages = conn.execute(
+        &format!(
+            "INSERT INTO {} (timestamp, topic, value) \
+             SELECT bucket, topic, value FROM temp.retention_buckets;",
+            table
+        ),
+        (),
+    )?;
+    conn.execute("DROP TABLE temp.retention_buckets;", ())?;
+
+    Ok(effect)
+}
+
+/// Matches the topics of `rule`, leaving out those covered by a more specific rule of the
+/// same table. Placeholders are numbered from `?1`.
+fn topic_condition(rule: &RetentionRule, rules: &[RetentionRule]) -> (String, Vec<ColumnKind>) {
+    let mut conditions: Vec<String> = vec![];
+    let mut params: Vec<ColumnKind> = vec![];
+
+    if !rule.topic_prefix.is_empty() {
+        conditions.push(under_prefix(&rule.topic_prefix, &mut params));
+    }
+
+    rules
+        .iter()
+        .filter(|other| {
+            other.table == rule.table
+                && other.topic_prefix != rule.topic_prefix
+                && is_under_prefix(&other.topic_prefix, &rule.topic_prefix)
+        })
+        .for_each(|other| {
+            conditions.push(format!(
+                "NOT {}",
+                under_prefix(&other.topic_prefix, &mut params)
+            ));
+        });
+
+    match conditions.is_empty() {
+        true => ("1".to_owned(), params),
+        false => (format!("({})", conditions.join(" AND ")), params),
+    }
+}
+
+/// `prefix` followed by the level separator, so that `/alarms` does not cover `/alarmsx`.
+fn level_prefix(prefix: &str) -> String {
+    match prefix.ends_with('/') {
+        true => prefix.to_owned(),
+        false => format!("{}/", prefix),
+    }
+}
+
+/// Whether `topic` is `prefix` or one of its subtopics, case-sensitive like MQTT topics.
+fn is_under_prefix(topic: &str, prefix: &str) -> bool {
+    prefix.is_empty() || topic == prefix || topic.starts_with(&level_prefix(prefix))
+}
+
+/// SQL counterpart of `is_under_prefix`. Compares with `substr` instead of `LIKE`, which
+/// ignores case.
+fn under_prefix(prefix: &str, params: &mut Vec<ColumnKind>) -> String {
+    params.push(ColumnKind::STRING(prefix.to_owned()));
+    params.push(ColumnKind::STRING(level_prefix(prefix)));
+    format!(
+        "(topic = ?{0} OR substr(topic, 1, length(?{1})) = ?{1})",
+        params.len() - 1,
+        params.len()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::migrations::{MIGRATIONS, migrate};
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn setup_test_db() -> Connection {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        migrate(&mut conn, MIGRATIONS).expect("Should create tables");
+        conn
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, (), |row| row.get(0))
+            .expect("Should count rows")
+    }
+
+    #[test]
+    fn should_parse_rules_that_format_back() {
+        for line in ["MEASUREMENTS * 30d 1h 2y", "LOGS /alarms 10d"] {
+            assert_eq!(RetentionRule::parse(line).unwrap().to_string(), line);
+        }
+        let rule = RetentionRule::parse("logs * 1w").unwrap();
+        assert_eq!(rule.table, DataTable::Logs);
+        assert_eq!(rule.topic_prefix, "");
+
+        assert!(RetentionRule::parse("LOGS * 7d 1h 30d").is_err());
+        assert!(RetentionRule::parse("MEASUREMENTS * 30d 1h 7d").is_err());
+        assert!(RetentionRule::parse("MEASUREMENTS * 0d").is_err());
+        assert!(RetentionRule::parse("DEVICES * 7d").is_err());
+        assert!(RetentionRule::parse("LOGS 7d").is_err());
+    }
+
+    #[test]
+    fn should_drop_old_rows_with_specific_rules_first() {
+        let mut conn = setup_test_db();
+        conn.execute(
+            "INSERT INTO LOGS VALUES (?1, '/door', 'open'), (?1, '/alarms/door', 'on'), (?2, '/door', 'closed');",
+            [NOW - 10 * DAY, NOW - DAY],
+        )
+        .expect("Should insert rows");
+        let rules = vec![
+            RetentionRule::parse("LOGS * 7d").unwrap(),
+            RetentionRule::parse("LOGS /alarms 30d").unwrap(),
+        ];
+
+        let preview = run_rules(&mut conn, &rules, NOW, false).unwrap();
+        assert_eq!(preview[0].dropped, 1);
+        assert_eq!(preview[1].dropped, 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM LOGS"), 3);
+
+        run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM LOGS WHERE topic = '/alarms/door'"
+            ),
+            1
+        );
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM LOGS"), 2);
+    }
+
+    #[test]
+    fn should_keep_text_values_when_averaging() {
+        let mut conn = setup_test_db();
+        let old_hour = ((NOW - 40 * DAY) / 3600) * 3600;
+        conn.execute(
+            "INSERT INTO MEASUREMENTS VALUES \
+             (?1, '/temp', 20.0), (?1 + 60, '/temp', 22.0), (?1 + 120, '/temp', 'error');",
+            [old_hour],
+        )
+        .expect("Should insert rows");
+        let rules = vec![RetentionRule::parse("MEASUREMENTS * 30d 1h 2y").unwrap()];
+
+        let effects = run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(effects[0].averaged, 2);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM MEASUREMENTS WHERE value = 'error'"
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM MEASUREMENTS WHERE value = 21.0"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn should_average_null_topics_once() {
+        let mut conn = setup_test_db();
+        let old_hour = ((NOW - 40 * DAY) / 3600) * 3600;
+        conn.execute(
+            "INSERT INTO MEASUREMENTS VALUES (?1, NULL, 20.0), (?1 + 60, NULL, 22.0);",
+            [old_hour],
+        )
+        .expect("Should insert rows");
+        let rules = vec![RetentionRule::parse("MEASUREMENTS * 30d 1h 2y").unwrap()];
+
+        let effects = run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(effects[0].averaged, 2);
+        assert_eq!(effects[0].averages, 1);
+        let effects = run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(effects[0].averages, 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM MEASUREMENTS"), 1);
+    }
+
+    #[test]
+    fn should_match_whole_levels_with_case() {
+        let mut conn = setup_test_db();
+        conn.execute(
+            "INSERT INTO LOGS VALUES (?1, '/alarms', 'on'), (?1, '/alarms/door', 'on'), \
+             (?1, '/Alarms/door', 'on'), (?1, '/alarmsx', 'on');",
+            [NOW - 10 * DAY],
+        )
+        .expect("Should insert rows");
+        let rules = vec![
+            RetentionRule::parse("LOGS * 7d").unwrap(),
+            RetentionRule::parse("LOGS /alarms 30d").unwrap(),
+        ];
+
+        let effects = run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(effects[0].dropped, 2);
+        assert_eq!(effects[1].dropped, 0);
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM LOGS WHERE topic IN ('/alarms', '/alarms/door')"
+            ),
+            2
+        );
+        assert!(is_under_prefix("/alarms/door", "/alarms/"));
+        assert!(!is_under_prefix("/alarmsx", "/alarms"));
+    }
+
+    #[test]
+    fn should_average_old_measurements_once() {
+        let mut conn = setup_test_db();
+        let old_hour = ((NOW - 40 * DAY) / 3600) * 3600;
+        conn.execute(
+            "INSERT INTO MEASUREMENTS VALUES \
+             (?1, '/temp', 20.0), (?1 + 60, '/temp', 22.0), (?1 + 3660, '/temp', 30.0), \
+             (?2, '/temp', 25.0), (?3, '/temp', 1.0);",
+            [old_hour, NOW - DAY, NOW - 3 * 365 * DAY],
+        )
+        .expect("Should insert rows");
+        let rules = vec![RetentionRule::parse("MEASUREMENTS * 30d 1h 2y").unwrap()];
+
+        let effects = run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(effects[0].dropped, 1);
+        assert_eq!(effects[0].averaged, 3);
+        assert_eq!(effects[0].averages, 2);
+        let average: f64 = conn
+            .query_row(
+                "SELECT value FROM MEASUREMENTS WHERE timestamp = ?1",
+                [old_hour],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(average, 21.0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM MEASUREMENTS"), 3);
+
+        let effects = run_rules(&mut conn, &rules, NOW, true).unwrap();
+        assert_eq!(effects[0].averaged, 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM MEASUREMENTS"), 3);
+    }
+}
@@ -0,0 +1,314 @@
+use chrono::{DateTime, Local};
+use serde_json::Value;
+
+use crate::db_interactions::{ColumnKind, DBRow, is_timestamp_column};
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Bytes per line of a hex dump.
+const DUMP_WIDTH: usize = 16;
+/// Shorter values decode as hex or base64 by accident too often to be worth showing.
+const MIN_ENCODED_LEN: usize = 8;
+
+/// Titled block of the row detail pane.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetailSection {
+    pub title: String,
+    pub body: String,
+}
+
+/// Every value of `row` in full, with timestamps in local time, UTC and as epoch, and
+/// values that look like JSON, hex or base64 decoded below them.
+pub fn describe_row(row: &DBRow) -> Vec<DetailSection> {
+    row.columns
+        .iter()
+        .zip(&row.values)
+        .flat_map(|(column, value)| {
+            let title = match column.decl_type.is_empty() {
+                true => column.name.clone(),
+                false => format!("{} ({})", column.name, column.decl_type),
+            };
+            let body = match value {
+                ColumnKind::INTEGER(timestamp) if is_timestamp_column(column) => {
+                    describe_timestamp(*timestamp)
+                }
+                ColumnKind::BLOB(bytes) => hex_dump(bytes),
+                value => value.to_raw_string(),
+            };
+            let mut sections = vec![DetailSection { title, body }];
+            sections.extend(decode_value(value).into_iter().map(|(encoding, body)| {
+                DetailSection {
+                    title: format!("{} as {}", column.name, encoding),
+                    body,
+                }
+            }));
+            sections
+        })
+        .collect()
+}
+
+fn describe_timestamp(timestamp: i64) -> String {
+    match DateTime::from_timestamp(timestamp, 0) {
+        Some(utc) => format!(
+            "{}\n{}\n{}",
+            utc.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S %:z"),
+            utc.format("%Y-%m-%d %H:%M:%S UTC"),
+            timestamp
+        ),
+        None => timestamp.to_string(),
+    }
+}
+
+/// Readable forms of `value`, named by the encoding they were decoded from.
+fn decode_value(value: &ColumnKind) -> Vec<(&'static str, String)> {
+    let text = match value {
+        ColumnKind::STRING(text) => text.trim(),
+        ColumnKind::BLOB(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => text.trim(),
+            Err(_) => return vec![],
+        },
+        _ => return vec![],
+    };
+
+    let mut decoded = vec![];
+    if let Ok(json @ (Value::Object(_) | Value::Array(_))) = serde_json::from_str::<Value>(text) {
+        decoded.push(("JSON", json_tree(&json)));
+    }
+    if let Some(bytes) = decode_hex(text) {
+        decoded.push(("hex", describe_bytes(&bytes)));
+    }
+    // Plain words decode as base64 too, so those only count when they decode to text.
+    if let Some(bytes) = decode_base64(text)
+        && (text.contains(['+', '=']) || is_text(&bytes))
+    {
+        decoded.push(("base64", describe_bytes(&bytes)));
+    }
+    decoded
+}
+
+/// Nested JSON drawn as a tree, one key or array item per line.
+fn json_tree(value: &Value) -> String {
+    let mut lines = vec![];
+    add_tree_lines(value, "", &mut lines);
+    match lines.is_empty() {
+        true => value.to_string(),
+        false => lines.join("\n"),
+    }
+}
+
+fn add_tree_lines(value: &Value, indent: &str, lines: &mut Vec<String>) {
+    let children: Vec<(String, &Value)> = match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| (key.clone(), value))
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| (format!("[{}]", idx), value))
+            .collect(),
+        _ => return,
+    };
+
+    let last = children.len().saturating_sub(1);
+    children.iter().enumerate().for_each(|(idx, (key, value))| {
+        let (branch, nested_indent) = match idx == last {
+            true => ("└─ ", "   "),
+            false => ("├─ ", "│  "),
+        };
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                lines.push(format!("{}{}{}", indent, branch, key))
+            }
+            Value::Array(items) if !items.is_empty() => {
+                lines.push(format!("{}{}{}", indent, branch, key))
+            }
+            _ => lines.push(format!("{}{}{}: {}", indent, branch, key, value)),
+        }
+        add_tree_lines(value, &format!("{}{}", indent, nested_indent), lines);
+    });
+}
+
+/// Bytes of an even number of hex digits, spaces and a `0x` prefix allowed.
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let digits: String = text
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|char| !char.is_whitespace())
+        .collect();
+    if digits.len() < MIN_ENCODED_LEN
+        || !digits.len().is_multiple_of(2)
+        || !digits.chars().all(|char| char.is_ascii_hexdigit())
+        || digits.chars().all(|char| char.is_ascii_digit())
+    {
+        return None;
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&digits[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+/// Bytes of padded standard base64.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if text.len() < MIN_ENCODED_LEN || !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let data = text
+        .strip_suffix(b"==")
+        .or(text.strip_suffix(b"="))
+        .unwrap_or(text);
+
+    let mut bytes = vec![];
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for char in data {
+        let sextet = BASE64_ALPHABET.iter().position(|c| c == char)? as u32;
+        buffer = (buffer << 6) | sextet;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+fn is_text(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(|text| {
+        !text
+            .chars()
+            .any(|char| char.is_control() && !char.is_whitespace())
+    })
+}
+
+/// Decoded bytes as text when they are printable UTF-8, and as a hex dump.
+fn describe_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if is_text(bytes) => match serde_json::from_str::<Value>(text) {
+            Ok(json @ (Value::Object(_) | Value::Array(_))) => {
+                format!("{}\n\n{}", json_tree(&json), hex_dump(bytes))
+            }
+            _ => format!("{}\n\n{}", text, hex_dump(bytes)),
+        },
+        _ => hex_dump(bytes),
+    }
+}
+
+/// Offset, hex and printable ASCII of `bytes`, `DUMP_WIDTH` bytes per line.
+fn hex_dump(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "<0 bytes>".to_owned();
+    }
+    bytes
+        .chunks(DUMP_WIDTH)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|byte| match byte.is_ascii_graphic() || *byte == b' ' {
+                    true => *byte as char,
+                    false => '.',
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                idx * DUMP_WIDTH,
+                hex.join(" "),
+                ascii,
+                width = DUMP_WIDTH * 3 - 1
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db_interactions::DataTable;
+
+    #[test]
+    fn should_draw_json_as_tree() {
+        let json: Value =
+            serde_json::from_str(r#"{"temp": 21.5, "sensors": ["a", {"id": 2}], "ok": true}"#)
+                .unwrap();
+        assert_eq!(
+            json_tree(&json),
+            "├─ ok: true\n\
+             ├─ sensors\n\
+             │  ├─ [0]: \"a\"\n\
+             │  └─ [1]\n\
+             │     └─ id: 2\n\
+             └─ temp: 21.5"
+        );
+    }
+
+    #[test]
+    fn should_decode_hex_and_base64() {
+        assert_eq!(decode_hex("48656c"), None, "Too short to tell");
+        assert_eq!(decode_hex("12345678"), None, "Numbers are not hex");
+        assert_eq!(
+            decode_hex("0x4865 6c6c 6f21 2121"),
+            Some(b"Hello!!!".to_vec())
+        );
+        assert_eq!(decode_hex("48656c6c6f2"), None);
+        assert_eq!(decode_hex("hello world!"), None);
+
+        assert_eq!(
+            decode_base64("SGVsbG8gd29ybGQ="),
+            Some(b"Hello world".to_vec())
+        );
+        assert_eq!(decode_base64("eyJhIjoxfQ=="), Some(br#"{"a":1}"#.to_vec()));
+        assert_eq!(decode_base64("SGVsbG8gd29ybGQ"), None);
+        assert_eq!(decode_base64("not base64!!"), None);
+
+        let decoded = |text: &str| -> Vec<&str> {
+            decode_value(&ColumnKind::STRING(text.to_owned()))
+                .iter()
+                .map(|(encoding, _)| *encoding)
+                .collect()
+        };
+        assert_eq!(decoded("SGVsbG8gd29ybGQ="), vec!["base64"]);
+        assert!(decoded("kitchen1").is_empty(), "Words are not base64");
+        assert_eq!(decoded("[1, 2]"), vec!["JSON"]);
+        assert!(decoded("21.5").is_empty());
+    }
+
+    #[test]
+    fn should_describe_every_value_in_full() {
+        let long_topic = "/home/ground-floor/kitchen/sensors/temperature";
+        let row = DBRow {
+            columns: DataTable::Measurements.get_columns(),
+            values: vec![
+                ColumnKind::INTEGER(1_700_000_000),
+                ColumnKind::STRING(long_topic.to_owned()),
+                ColumnKind::STRING(r#"{"value": 21.5}"#.to_owned()),
+            ],
+            rowid: Some(1),
+        };
+
+        let sections = describe_row(&row);
+        let titles: Vec<&str> = sections
+            .iter()
+            .map(|section| section.title.as_str())
+            .collect();
+        assert_eq!(titles.len(), 4);
+        assert_eq!(titles[3], "value as JSON");
+        assert!(sections[0].body.contains("2023-11-14 22:13:20 UTC"));
+        assert!(sections[0].body.ends_with("1700000000"));
+        assert_eq!(sections[1].body, long_topic);
+        assert_eq!(sections[3].body, "└─ value: 21.5");
+    }
+
+    #[test]
+    fn should_dump_bytes() {
+        assert_eq!(
+            hex_dump(b"Hi\x00"),
+            format!("00000000  48 69 00{}  |Hi.|", " ".repeat(39))
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::Error;
+
+use anyhow::Result;
+use rusqlite::{Connection, params_from_iter};
+
+use crate::{
+    cli_args::ARGS,
+    db_interactions::{ColumnKind, TableFilter, query_table_columns, quote_identifier},
+    time_range::TimeRange,
+};
+
+/// Columns a table needs for the STATS view.
+const STATS_COLUMNS: [&str; 3] = ["timestamp", "topic", "value"];
+
+/// Width of the time buckets rows are grouped into. Buckets are aligned to UTC.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatsBucket {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl StatsBucket {
+    pub const ALL: [StatsBucket; 3] = [StatsBucket::Minute, StatsBucket::Hour, StatsBucket::Day];
+
+    pub fn get_title(&self) -> &str {
+        match self {
+            StatsBucket::Minute => "Minute",
+            StatsBucket::Hour => "Hour",
+            StatsBucket::Day => "Day",
+        }
+    }
+
+    fn get_seconds(&self) -> i64 {
+        match self {
+            StatsBucket::Minute => 60,
+            StatsBucket::Hour => 60 * 60,
+            StatsBucket::Day => 24 * 60 * 60,
+        }
+    }
+}
+
+/// Numeric values of one topic in one bucket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopicStats {
+    pub topic: String,
+    /// Unix timestamp of the start of the bucket.
+    pub bucket_start: i64,
+    pub count: i64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    /// Value with the latest timestamp in the bucket.
+    pub last: f64,
+}
+
+impl TopicStats {
+    pub fn format_header() -> String {
+        format!(
+            "| {:<19} | {:<24} | {:>8} | {:>10} | {:>10} | {:>10} | {:>10}",
+            "bucket", "topic", "count", "min", "max", "avg", "last"
+        )
+    }
+}
+
+/// Lined up with `TopicStats::format_header`.
+impl From<&TopicStats> for String {
+    fn from(value: &TopicStats) -> Self {
+        let topic: String = value.topic.chars().take(24).collect();
+        format!(
+            "| {:<19} | {:<24} | {:>8} | {:>10.2} | {:>10.2} | {:>10.2} | {:>10.2}",
+            TimeRange::format_bound(Some(value.bucket_start)),
+            topic,
+            value.count,
+            value.min,
+            value.max,
+            value.avg,
+            value.last
+        )
+    }
+}
+
+/// Groups the numeric values of `table_name` matching `filter` by topic and `bucket`, newest
+/// bucket first. Returns at most `limit` groups.
+pub fn get_topic_stats(
+    table_name: &str,
+    filter: &TableFilter,
+    bucket: StatsBucket,
+    limit: usize,
+) -> Result<Vec<TopicStats>> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_topic_stats(&conn, table_name, filter, bucket, limit)
+}
+
+fn query_topic_stats(
+    conn: &Connection,
+    table_name: &str,
+    filter: &TableFilter,
+    bucket: StatsBucket,
+    limit: usize,
+) -> Result<Vec<TopicStats>> {
+    let columns = query_table_columns(conn, table_name)?;
+    if let Some(missing) = STATS_COLUMNS
+        .iter()
+        .find(|name| !columns.iter().any(|column| column.name == **name))
+    {
+        return Err(Error::other(format!("This table has no {} column.", missing)).into());
+    }
+
+    let (where_clause, mut params) = filter.where_clause(&columns)?;
+    let numeric = "typeof(value) IN ('integer', 'real')";
+    let where_clause = match where_clause.is_empty() {
+        true => format!("WHERE {}", numeric),
+        false => format!("{} AND {}", where_clause, numeric),
+    };
+    let bucket_param = params.len() + 1;
+    let limit_param = params.len() + 2;
+    params.push(ColumnKind::INTEGER(bucket.get_seconds()));
+    params.push(ColumnKind::INTEGER(limit as i64));
+
+    let sql = format!(
+        "WITH bucketed AS ( \
+            SELECT COALESCE(topic, '') AS topic, (timestamp / ?{1}) * ?{1} AS bucket, value, \
+            ROW_NUMBER() OVER ( \
+                PARTITION BY topic, timestamp / ?{1} ORDER BY timestamp DESC \
+            ) AS recency \
+            FROM {0} {2} \
+        ) \
+        SELECT topic, bucket, COUNT(*), MIN(value), MAX(value), AVG(value), \
+        MAX(CASE WHEN recency = 1 THEN value END) \
+        FROM bucketed GROUP BY topic, bucket ORDER BY bucket DESC, topic LIMIT ?{3};",
+        quote_identifier(table_name),
+        bucket_param,
+        where_clause,
+        limit_param
+    );
+
+    let mut statement = conn.prepare(&sql)?;
+    let stats = statement
+        .query_map(params_from_iter(params.iter()), |row| {
+            Ok(TopicStats {
+                topic: row.get(0)?,
+                bucket_start: row.get(1)?,
+                count: row.get(2)?,
+                min: row.get(3)?,
+                max: row.get(4)?,
+                avg: row.get(5)?,
+                last: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<TopicStats>, rusqlite::Error>>()?;
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::migrations::{MIGRATIONS, migrate};
+
+    fn setup_test_db() -> Connection {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        migrate(&mut conn, MIGRATIONS).expect("Should create tables");
+        conn.execute_batch(
+            "
+            INSERT INTO MEASUREMENTS VALUES
+                (3600, '/temp', 20.0), (3700, '/temp', 24.0), (3650, '/temp', 22.0),
+                (7200, '/temp', 30.0), (3600, '/hum', 50.0), (3601, '/hum', 'error');
+            ",
+        )
+        .expect("Should insert rows");
+        conn
+    }
+
+    #[test]
+    fn should_group_by_topic_and_bucket() {
+        let conn = setup_test_db();
+        let stats = query_topic_stats(
+            &conn,
+            "MEASUREMENTS",
+            &TableFilter::default(),
+            StatsBucket::Hour,
+            10,
+        )
+        .unwrap();
+
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].bucket_start, 7200);
+        assert_eq!(
+            stats[2],
+            TopicStats {
+                topic: "/temp".to_owned(),
+                bucket_start: 3600,
+                count: 3,
+                min: 20.0,
+                max: 24.0,
+                avg: 22.0,
+                last: 24.0,
+            }
+        );
+        // Text values are left out.
+        assert_eq!(stats[1].topic, "/hum");
+        assert_eq!(stats[1].count, 1);
+    }
+
+    #[test]
+    fn should_apply_filter_and_limit() {
+        let conn = setup_test_db();
+        let filter = TableFilter {
+            text: "temp".to_owned(),
+            ..Default::default()
+        };
+
+        let stats =
+            query_topic_stats(&conn, "MEASUREMENTS", &filter, StatsBucket::Minute, 10).unwrap();
+        assert_eq!(stats.len(), 3);
+        assert!(stats.iter().all(|stats| stats.topic == "/temp"));
+
+        let stats = query_topic_stats(&conn, "MEASUREMENTS", &filter, StatsBucket::Day, 1).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].count, 4);
+    }
+
+    #[test]
+    fn should_need_timestamp_topic_and_value() {
+        let conn = setup_test_db();
+        conn.execute("CREATE TABLE DEVICES (id integer, name text);", ())
+            .unwrap();
+        assert!(
+            query_topic_stats(
+                &conn,
+                "DEVICES",
+                &TableFilter::default(),
+                StatsBucket::Hour,
+                10
+            )
+            .is_err()
+        );
+    }
+}
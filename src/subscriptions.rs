@@ -0,0 +1,237 @@
+use std::{
+    fmt::Display,
+    fs,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use cursive::theme::Color;
+use mosquitto_rs::QoS;
+
+use crate::{
+    cli_args::ARGS,
+    publish::{qos_from_level, qos_level},
+    utils::config_dir,
+};
+
+/// Colors offered for subscriptions, `default` leaves the terminal color.
+pub const COLORS: [&str; 7] = [
+    "default", "red", "green", "yellow", "blue", "magenta", "cyan",
+];
+
+/// A topic filter of the LOGS screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subscription {
+    pub filter: String,
+    pub qos: QoS,
+    /// Name `Color::parse` understands, messages matching the filter are shown in it.
+    pub color: String,
+    /// Disabled subscriptions stay in the list without being subscribed to.
+    pub enabled: bool,
+}
+
+/// `on 1 red /alarms/#`, as the config file stores it. The filter goes last as it can
+/// contain spaces.
+impl Display for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            if self.enabled { "on" } else { "off" },
+            qos_level(self.qos),
+            self.color,
+            self.filter
+        )
+    }
+}
+
+impl Subscription {
+    pub fn new(filter: &str) -> Subscription {
+        Subscription {
+            filter: filter.to_owned(),
+            qos: QoS::AtMostOnce,
+            color: COLORS[0].to_owned(),
+            enabled: true,
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Subscription> {
+        let invalid = || Error::other(format!("Expected on|off QOS COLOR FILTER, got {}", line));
+        let mut parts = line.trim().splitn(4, ' ');
+        let mut next = || parts.next().ok_or_else(invalid);
+
+        let enabled = match next()? {
+            "on" => true,
+            "off" => false,
+            _ => return Err(invalid().into()),
+        };
+        let qos = next()?
+            .parse()
+            .ok()
+            .and_then(qos_from_level)
+            .ok_or_else(invalid)?;
+        let color = next()?.to_owned();
+        if Color::parse(&color).is_none() {
+            return Err(Error::other(format!("Unknown color {}", color)).into());
+        }
+        let filter = next()?.to_owned();
+        validate_filter(&filter)?;
+
+        Ok(Subscription {
+            filter,
+            qos,
+            color,
+            enabled,
+        })
+    }
+
+    pub fn get_color(&self) -> Color {
+        Color::parse(&self.color).unwrap_or(Color::TerminalDefault)
+    }
+
+    /// One line for the subscription list.
+    pub fn describe(&self) -> String {
+        format!(
+            "[{}] QoS {} {}",
+            if self.enabled { "x" } else { " " },
+            qos_level(self.qos),
+            self.filter
+        )
+    }
+}
+
+/// Checks `+` and `#` fill a whole level and `#` is the last one.
+pub fn validate_filter(filter: &str) -> Result<()> {
+    let levels: Vec<&str> = filter.split('/').collect();
+    let invalid = filter.is_empty()
+        || levels.iter().enumerate().any(|(idx, level)| {
+            (level.contains('#') && (*level != "#" || idx + 1 != levels.len()))
+                || (level.contains('+') && *level != "+")
+        });
+    if invalid {
+        return Err(Error::other(format!(
+            "Invalid topic filter {}, + and # have to fill a whole level and # has to be last.",
+            filter
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Whether `topic` matches the MQTT `filter`. `a/#` matches `a` too, and wildcards in the
+/// first level do not match topics starting with `$`.
+pub fn filter_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+
+    let mut topic_levels = topic.split('/');
+    for level in filter.split('/') {
+        match (level, topic_levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (level, Some(topic_level)) if level == topic_level => {}
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+/// First enabled subscription that `topic` matches.
+pub fn find_subscription<'a>(
+    subscriptions: &'a [Subscription],
+    topic: &str,
+) -> Option<&'a Subscription> {
+    subscriptions
+        .iter()
+        .find(|subscription| subscription.enabled && filter_matches(&subscription.filter, topic))
+}
+
+fn subscriptions_path() -> PathBuf {
+    config_dir().join("subscriptions.txt")
+}
+
+/// Saved subscriptions, or one for `ARGS.topic` before any were saved.
+pub fn load_subscriptions() -> Result<Vec<Subscription>> {
+    match fs::read_to_string(subscriptions_path()) {
+        Ok(content) => content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(Subscription::parse)
+            .collect(),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![Subscription::new(&ARGS.topic)]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_subscriptions(subscriptions: &[Subscription]) -> Result<()> {
+    let lines: Vec<String> = subscriptions
+        .iter()
+        .map(|subscription| subscription.to_string())
+        .collect();
+    fs::create_dir_all(config_dir())?;
+    fs::write(subscriptions_path(), format!("{}\n", lines.join("\n")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_match_topics_like_the_broker() {
+        assert!(filter_matches(
+            "/home/+/temperature",
+            "/home/kitchen/temperature"
+        ));
+        assert!(!filter_matches(
+            "/home/+/temperature",
+            "/home/kitchen/humidity"
+        ));
+        assert!(!filter_matches(
+            "/home/+/temperature",
+            "/home/a/b/temperature"
+        ));
+        assert!(filter_matches("/alarms/#", "/alarms"));
+        assert!(filter_matches("/alarms/#", "/alarms/door/front"));
+        assert!(!filter_matches("/alarms/#", "/alarmsx/door"));
+        assert!(filter_matches("#", "/anything"));
+        assert!(!filter_matches("#", "$SYS/broker/uptime"));
+        assert!(filter_matches("$SYS/#", "$SYS/broker/uptime"));
+        assert!(!filter_matches("/home", "/home/kitchen"));
+        assert!(filter_matches("+", "kitchen"));
+        assert!(!filter_matches("+", "/kitchen"));
+    }
+
+    #[test]
+    fn should_tag_with_first_enabled_match() {
+        let mut alarms = Subscription::new("/alarms/#");
+        alarms.enabled = false;
+        let subscriptions = vec![alarms, Subscription::new("#")];
+        assert_eq!(
+            find_subscription(&subscriptions, "/alarms/door").map(|s| s.filter.as_str()),
+            Some("#")
+        );
+    }
+
+    #[test]
+    fn should_parse_saved_subscriptions() {
+        let subscription = Subscription {
+            filter: "/living room/+".to_owned(),
+            qos: QoS::ExactlyOnce,
+            color: "cyan".to_owned(),
+            enabled: false,
+        };
+        assert_eq!(subscription.to_string(), "off 2 cyan /living room/+");
+        assert_eq!(
+            Subscription::parse(&subscription.to_string()).unwrap(),
+            subscription
+        );
+        assert!(Subscription::parse("on 3 red /a").is_err());
+        assert!(Subscription::parse("on 1 plaid /a").is_err());
+        assert!(Subscription::parse("on 1 red /a/#/b").is_err());
+        assert!(Subscription::parse("on 1 red").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use unicode_width::UnicodeWidthChar;
+
+use crate::db_interactions::DBRow;
+
+/// Widest a column gets from its values alone, wider ones are cut off.
+const MAX_AUTO_WIDTH: usize = 40;
+/// Narrowest a column is shrunk to when the row does not fit the screen.
+const MIN_AUTO_WIDTH: usize = 4;
+const ROW_PREFIX: &str = "| ";
+const SEPARATOR: &str = " | ";
+const ELLIPSIS: char = '…';
+
+/// Display widths of the columns of a table, in terminal cells.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TableLayout {
+    pub widths: Vec<usize>,
+}
+
+impl TableLayout {
+    /// Makes every column as wide as its header and widest value, up to `MAX_AUTO_WIDTH`,
+    /// or as wide as given in `fixed`. The widest columns without a fixed width are then
+    /// narrowed until a row fits into `available` cells.
+    pub fn fit(
+        headers: &[String],
+        rows: &[Vec<String>],
+        fixed: &[Option<usize>],
+        available: usize,
+    ) -> Self {
+        let widths: Vec<usize> = headers
+            .iter()
+            .enumerate()
+            .map(|(idx, header)| match fixed.get(idx).copied().flatten() {
+                Some(width) => width,
+                None => rows
+                    .iter()
+                    .filter_map(|cells| cells.get(idx))
+                    .map(|cell| display_width(cell))
+                    .fold(display_width(header), usize::max)
+                    .clamp(MIN_AUTO_WIDTH, MAX_AUTO_WIDTH),
+            })
+            .collect();
+
+        let mut layout = TableLayout { widths };
+        while layout.row_width() > available {
+            let widths = &layout.widths;
+            let widest = (0..widths.len())
+                .filter(|idx| fixed.get(*idx).copied().flatten().is_none())
+                .filter(|idx| widths[*idx] > MIN_AUTO_WIDTH)
+                .max_by_key(|idx| (widths[*idx], usize::MAX - idx));
+            let Some(widest) = widest else {
+                break;
+            };
+            layout.widths[widest] -= 1;
+        }
+
+        layout
+    }
+
+    /// Cells taken by a row, separators included.
+    pub fn row_width(&self) -> usize {
+        let separators = self.widths.len().saturating_sub(1) * SEPARATOR.len();
+        ROW_PREFIX.len() + self.widths.iter().sum::<usize>() + separators
+    }
+
+    /// Lines `cells` up in their columns, cutting off and padding them to the widths.
+    pub fn format(&self, cells: &[String]) -> String {
+        let cells: Vec<String> = self
+            .widths
+            .iter()
+            .enumerate()
+            .map(|(idx, width)| fit_to_width(cells.get(idx).map_or("", |cell| cell), *width))
+            .collect();
+        format!("{}{}", ROW_PREFIX, cells.join(SEPARATOR))
+    }
+
+    pub fn format_row(&self, row: &DBRow) -> String {
+        self.format(&row.display_values())
+    }
+
+    /// Column shown at cell `x` of a formatted row. The separator after a column counts to it.
+    pub fn column_at(&self, x: usize) -> Option<usize> {
+        let mut end = ROW_PREFIX.len();
+        self.widths.iter().position(|width| {
+            end += width + SEPARATOR.len();
+            x < end
+        })
+    }
+}
+
+/// Terminal cells `text` takes. Wide characters take two, combining characters none.
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Pads `text` to exactly `width` cells, or cuts it off with an ellipsis. Line breaks and
+/// other control characters become spaces so a value stays on its row.
+pub fn fit_to_width(text: &str, width: usize) -> String {
+    let text: String = text
+        .chars()
+        .map(|char| if char.is_control() { ' ' } else { char })
+        .collect();
+    let text_width = display_width(&text);
+    if text_width <= width {
+        return format!("{}{}", text, " ".repeat(width - text_width));
+    }
+    if width == 0 {
+        return "".to_owned();
+    }
+
+    let mut fitted = String::new();
+    let mut fitted_width = 0;
+    for char in text.chars() {
+        let char_width = char_width(char);
+        if fitted_width + char_width > width - 1 {
+            break;
+        }
+        fitted.push(char);
+        fitted_width += char_width;
+    }
+    fitted.push(ELLIPSIS);
+    // A wide character that did not fit leaves a gap.
+    format!("{}{}", fitted, " ".repeat(width - 1 - fitted_width))
+}
+
+fn char_width(char: char) -> usize {
+    char.width().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn should_measure_and_cut_by_display_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("温度"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+
+        assert_eq!(fit_to_width("abc", 5), "abc  ");
+        assert_eq!(fit_to_width("abcdef", 4), "abc…");
+        assert_eq!(fit_to_width("温度计", 4), "温… ");
+        assert_eq!(fit_to_width("a\nb", 3), "a b");
+        assert_eq!(fit_to_width("abc", 0), "");
+    }
+
+    #[test]
+    fn should_fit_columns_into_available_width() {
+        let headers = strings(&["timestamp", "topic", "value"]);
+        let rows = vec![strings(&[
+            "2025-06-01 14:00:00",
+            "/home/kitchen/temp",
+            "21.5",
+        ])];
+
+        let layout = TableLayout::fit(&headers, &rows, &[], 200);
+        assert_eq!(layout.widths, vec![19, 18, 5]);
+        assert_eq!(layout.row_width(), 2 + 42 + 6);
+
+        let layout = TableLayout::fit(&headers, &rows, &[], 40);
+        assert_eq!(layout.row_width(), 40);
+        assert_eq!(layout.widths, vec![13, 14, 5]);
+
+        let layout = TableLayout::fit(&headers, &rows, &[None, Some(25), None], 40);
+        assert_eq!(layout.widths, vec![4, 25, 4]);
+    }
+
+    #[test]
+    fn should_format_rows_and_find_columns() {
+        let layout = TableLayout { widths: vec![3, 5] };
+        let line = layout.format(&strings(&["ab", "abcdefg"]));
+        assert_eq!(line, "| ab  | abcd…");
+
+        assert_eq!(layout.column_at(0), Some(0));
+        assert_eq!(layout.column_at(4), Some(0));
+        assert_eq!(layout.column_at(7), Some(0));
+        assert_eq!(layout.column_at(8), Some(1));
+        assert_eq!(layout.column_at(20), None);
+    }
+}
@@ -0,0 +1,261 @@
+use std::io::Error;
+
+use anyhow::Result;
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+
+/// Formats accepted for absolute times, tried in order. Interpreted in local time.
+const DATE_TIME_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"];
+
+/// Shortcuts offered in the filter dialog. Anything `parse_shortcut` accepts works, these are
+/// just the common ones.
+pub const SHORTCUTS: [&str; 6] = [
+    "last 15m",
+    "last 1h",
+    "last 24h",
+    "last 7d",
+    "today",
+    "yesterday",
+];
+
+/// A half open range of unix timestamps, `from <= timestamp < to`. Missing bounds are open.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct TimeRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl TimeRange {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    /// Parses "last 15m", "last 2h", "today" and "yesterday" relative to `now`.
+    pub fn parse_shortcut(input: &str, now: DateTime<Local>) -> Result<TimeRange> {
+        let input = input.trim().to_lowercase();
+
+        if let Some(duration) = input.strip_prefix("last ") {
+            return Ok(TimeRange {
+                from: Some(duration_before(now, duration)?),
+                to: None,
+            });
+        }
+
+        let today = start_of_day(now.date_naive())?;
+        match input.as_str() {
+            "today" => Ok(TimeRange {
+                from: Some(today),
+                to: None,
+            }),
+            "yesterday" => {
+                let yesterday = now
+                    .date_naive()
+                    .checked_sub_days(Days::new(1))
+                    .ok_or(Error::other("Date out of range."))?;
+                Ok(TimeRange {
+                    from: Some(start_of_day(yesterday)?),
+                    to: Some(today),
+                })
+            }
+            _ => Err(Error::other(format!("Unknown time range: {}", input)).into()),
+        }
+    }
+
+    /// Formats a bound the same way `parse_time_bound` reads it back.
+    pub fn format_bound(bound: Option<i64>) -> String {
+        bound
+            .and_then(|timestamp| Local.timestamp_opt(timestamp, 0).single())
+            .map(|time| time.format(DATE_TIME_FORMATS[0]).to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Parses one end of a range. Accepts an empty string (open bound), "now", a date, a date
+/// and time, a raw unix timestamp or a relative time such as "15m ago".
+pub fn parse_time_bound(input: &str, now: DateTime<Local>) -> Result<Option<i64>> {
+    let input = input.trim();
+    let lowercase = input.to_lowercase();
+
+    if input.is_empty() {
+        return Ok(None);
+    }
+    if lowercase == "now" {
+        return Ok(Some(now.timestamp()));
+    }
+    if let Some(duration) = lowercase.strip_suffix(" ago") {
+        return Ok(Some(duration_before(now, duration)?));
+    }
+    if let Ok(timestamp) = input.parse::<i64>() {
+        return Ok(Some(timestamp));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(Some(start_of_day(date)?));
+    }
+
+    DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(|time| Some(time.timestamp()))
+        .ok_or(Error::other(format!("Could not read time: {}", input)).into())
+}
+
+/// Parses durations like "15m", "2h", "7d", "30s" or "2y". A year is 365 days.
+pub fn parse_duration(input: &str) -> Result<TimeDelta> {
+    let input = input.trim();
+    let invalid = || Error::other(format!("Could not read duration: {}", input));
+    let unit = input.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = input[..input.len() - unit.len_utf8()]
+        .trim()
+        .parse()
+        .map_err(|_| invalid())?;
+
+    let delta = match unit {
+        's' => TimeDelta::try_seconds(amount),
+        'm' => TimeDelta::try_minutes(amount),
+        'h' => TimeDelta::try_hours(amount),
+        'd' => TimeDelta::try_days(amount),
+        'w' => TimeDelta::try_weeks(amount),
+        'y' => amount.checked_mul(365).and_then(TimeDelta::try_days),
+        _ => None,
+    };
+
+    delta.ok_or(Error::other(format!("Could not read duration: {}", input)).into())
+}
+
+/// Timestamp `duration` before `now`, an error when that is before the earliest date chrono
+/// can represent.
+fn duration_before(now: DateTime<Local>, duration: &str) -> Result<i64> {
+    now.checked_sub_signed(parse_duration(duration)?)
+        .map(|time| time.timestamp())
+        .ok_or(Error::other(format!("Could not read duration: {}", duration.trim())).into())
+}
+
+/// Formats a duration with the largest unit `parse_duration` reads that divides it evenly.
+pub fn format_duration(duration: TimeDelta) -> String {
+    let seconds = duration.num_seconds();
+    [
+        ("y", 365 * 24 * 60 * 60),
+        ("w", 7 * 24 * 60 * 60),
+        ("d", 24 * 60 * 60),
+        ("h", 60 * 60),
+        ("m", 60),
+    ]
+    .iter()
+    .find(|(_, unit)| seconds != 0 && seconds % unit == 0)
+    .map(|(name, unit)| format!("{}{}", seconds / unit, name))
+    .unwrap_or(format!("{}s", seconds))
+}
+
+fn start_of_day(date: NaiveDate) -> Result<i64> {
+    Local
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .map(|time| time.timestamp())
+        .ok_or(Error::other("Date out of range.").into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 6, 15, 12, 30, 0)
+            .single()
+            .expect("Should be a valid local time")
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        Local
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .single()
+            .expect("Should be a valid local time")
+            .timestamp()
+    }
+
+    #[test]
+    fn should_parse_last_shortcuts() {
+        let range = TimeRange::parse_shortcut("last 15m", now()).unwrap();
+        assert_eq!(range.from, Some(now().timestamp() - 15 * 60));
+        assert_eq!(range.to, None);
+
+        let range = TimeRange::parse_shortcut("Last 2h", now()).unwrap();
+        assert_eq!(range.from, Some(now().timestamp() - 2 * 60 * 60));
+    }
+
+    #[test]
+    fn should_parse_day_shortcuts() {
+        let today = TimeRange::parse_shortcut("today", now()).unwrap();
+        assert_eq!(today.from, Some(local(2025, 6, 15, 0, 0)));
+        assert_eq!(today.to, None);
+
+        let yesterday = TimeRange::parse_shortcut("yesterday", now()).unwrap();
+        assert_eq!(yesterday.from, Some(local(2025, 6, 14, 0, 0)));
+        assert_eq!(yesterday.to, Some(local(2025, 6, 15, 0, 0)));
+    }
+
+    #[test]
+    fn should_reject_unknown_shortcuts() {
+        assert!(TimeRange::parse_shortcut("last week", now()).is_err());
+        assert!(TimeRange::parse_shortcut("tomorrow", now()).is_err());
+    }
+
+    #[test]
+    fn should_reject_unknown_duration_units() {
+        assert!(parse_duration("5é").is_err());
+        assert!(parse_duration("3µ").is_err());
+        assert!(parse_duration("é").is_err());
+        assert!(parse_duration("").is_err());
+        assert_eq!(parse_duration(" 3 h").unwrap(), TimeDelta::hours(3));
+    }
+
+    #[test]
+    fn should_reject_durations_before_the_earliest_date() {
+        assert!(TimeRange::parse_shortcut("last 1000000y", now()).is_err());
+        assert!(parse_time_bound("1000000y ago", now()).is_err());
+        assert!(parse_time_bound("1000y ago", now()).is_ok());
+    }
+
+    #[test]
+    fn should_parse_bounds() {
+        assert_eq!(parse_time_bound("", now()).unwrap(), None);
+        assert_eq!(
+            parse_time_bound("now", now()).unwrap(),
+            Some(now().timestamp())
+        );
+        assert_eq!(
+            parse_time_bound("10m ago", now()).unwrap(),
+            Some(now().timestamp() - 600)
+        );
+        assert_eq!(
+            parse_time_bound("1700000000", now()).unwrap(),
+            Some(1700000000)
+        );
+        assert_eq!(
+            parse_time_bound("2025-06-01", now()).unwrap(),
+            Some(local(2025, 6, 1, 0, 0))
+        );
+        assert_eq!(
+            parse_time_bound("2025-06-01 14:05", now()).unwrap(),
+            Some(local(2025, 6, 1, 14, 5))
+        );
+        assert!(parse_time_bound("June first", now()).is_err());
+    }
+
+    #[test]
+    fn should_format_durations_that_parse_back() {
+        for input in ["90s", "15m", "36h", "30d", "2w", "2y"] {
+            let duration = parse_duration(input).unwrap();
+            assert_eq!(format_duration(duration), input);
+        }
+        assert_eq!(format_duration(parse_duration("14d").unwrap()), "2w");
+    }
+
+    #[test]
+    fn should_format_bounds_that_parse_back() {
+        let bound = Some(local(2025, 6, 1, 14, 5));
+        let formatted = TimeRange::format_bound(bound);
+        assert_eq!(parse_time_bound(&formatted, now()).unwrap(), bound);
+        assert_eq!(TimeRange::format_bound(None), "");
+    }
+}
@@ -0,0 +1,197 @@
+use std::{collections::HashSet, io::Error};
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::{
+    cli_args::ARGS,
+    db_interactions::{query_table_columns, quote_identifier},
+};
+
+/// A level of the stored topics with the rows of every topic below it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TopicNode {
+    /// Level name, empty for the level before a leading `/`.
+    pub name: String,
+    /// Topic up to and including this level.
+    pub path: String,
+    pub count: i64,
+    /// Latest timestamp of the subtree, when the table has a timestamp column.
+    pub last_seen: Option<i64>,
+    /// Sorted by name.
+    pub children: Vec<TopicNode>,
+}
+
+/// MQTT filter matching the level at `path` and everything below it.
+pub fn subtree_filter(path: &str) -> String {
+    format!("{}/#", path)
+}
+
+impl TopicNode {
+    /// Counts a topic given by its `levels` below this node. The root passes `is_root` so
+    /// its children are not prefixed with a `/`.
+    fn add(&mut self, levels: &[&str], count: i64, last_seen: Option<i64>, is_root: bool) {
+        self.count += count;
+        self.last_seen = self.last_seen.max(last_seen);
+
+        let Some((name, rest)) = levels.split_first() else {
+            return;
+        };
+        let idx = match self
+            .children
+            .binary_search_by(|child| child.name.as_str().cmp(name))
+        {
+            Ok(idx) => idx,
+            Err(idx) => {
+                let path = match is_root {
+                    true => name.to_string(),
+                    false => format!("{}/{}", self.path, name),
+                };
+                self.children.insert(
+                    idx,
+                    TopicNode {
+                        name: name.to_string(),
+                        path,
+                        ..Default::default()
+                    },
+                );
+                idx
+            }
+        };
+        self.children[idx].add(rest, count, last_seen, false);
+    }
+
+    /// Nodes shown when the nodes with a path in `expanded` are opened, with their depth.
+    pub fn visible(&self, expanded: &HashSet<String>) -> Vec<(usize, &TopicNode)> {
+        let mut rows = vec![];
+        self.children
+            .iter()
+            .for_each(|child| child.collect_visible(0, expanded, &mut rows));
+        rows
+    }
+
+    fn collect_visible<'a>(
+        &'a self,
+        depth: usize,
+        expanded: &HashSet<String>,
+        rows: &mut Vec<(usize, &'a TopicNode)>,
+    ) {
+        rows.push((depth, self));
+        if expanded.contains(&self.path) {
+            self.children
+                .iter()
+                .for_each(|child| child.collect_visible(depth + 1, expanded, rows));
+        }
+    }
+}
+
+/// Tree of the distinct topics of `table_name`. The returned root stands for the whole table.
+pub fn get_topic_tree(table_name: &str) -> Result<TopicNode> {
+    let conn = Connection::open(&ARGS.db_path)?;
+    query_topic_tree(&conn, table_name)
+}
+
+fn query_topic_tree(conn: &Connection, table_name: &str) -> Result<TopicNode> {
+    let columns = query_table_columns(conn, table_name)?;
+    if !columns.iter().any(|column| column.name == "topic") {
+        return Err(Error::other("This table has no topic column.").into());
+    }
+    let last_seen = match columns.iter().any(|column| column.name == "timestamp") {
+        true => "MAX(timestamp)",
+        false => "NULL",
+    };
+
+    let mut statement = conn.prepare(&format!(
+        "SELECT topic, COUNT(*), {} FROM {} WHERE topic IS NOT NULL GROUP BY topic;",
+        last_seen,
+        quote_identifier(table_name)
+    ))?;
+    let mut root = TopicNode::default();
+    statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<usize, String>(0)?,
+                row.get::<usize, i64>(1)?,
+                row.get::<usize, Option<i64>>(2)?,
+            ))
+        })?
+        .try_for_each(|topic| -> Result<()> {
+            let (topic, count, last_seen) = topic?;
+            let levels: Vec<&str> = topic.split('/').collect();
+            root.add(&levels, count, last_seen, true);
+            Ok(())
+        })?;
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::migrations::{MIGRATIONS, migrate};
+
+    fn setup_test_db() -> Connection {
+        let mut conn = Connection::open_in_memory().expect("Should be able to open in memory db");
+        migrate(&mut conn, MIGRATIONS).expect("Should create tables");
+        conn.execute_batch(
+            "
+            INSERT INTO MEASUREMENTS VALUES
+                (10, '/home/kitchen/temp', 20.0), (30, '/home/kitchen/temp', 21.0),
+                (20, '/home/hall/temp', 18.0), (40, 'garden', 12.0), (50, NULL, 1.0);
+            ",
+        )
+        .expect("Should insert rows");
+        conn
+    }
+
+    #[test]
+    fn should_build_tree_with_counts_and_last_seen() {
+        let conn = setup_test_db();
+        let root = query_topic_tree(&conn, "MEASUREMENTS").unwrap();
+
+        assert_eq!(root.count, 4);
+        let names: Vec<&str> = root
+            .children
+            .iter()
+            .map(|node| node.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["", "garden"]);
+
+        let home = &root.children[0].children[0];
+        assert_eq!(home.path, "/home");
+        assert_eq!(subtree_filter(&home.path), "/home/#");
+        assert_eq!((home.count, home.last_seen), (3, Some(30)));
+        let kitchen = &home.children[1];
+        assert_eq!(kitchen.path, "/home/kitchen");
+        assert_eq!((kitchen.count, kitchen.last_seen), (2, Some(30)));
+        assert_eq!(root.children[1].path, "garden");
+    }
+
+    #[test]
+    fn should_only_show_expanded_nodes() {
+        let conn = setup_test_db();
+        let root = query_topic_tree(&conn, "MEASUREMENTS").unwrap();
+
+        let paths = |expanded: &[&str]| -> Vec<(usize, String)> {
+            let expanded = expanded.iter().map(|path| path.to_string()).collect();
+            root.visible(&expanded)
+                .iter()
+                .map(|(depth, node)| (*depth, node.path.clone()))
+                .collect()
+        };
+        assert_eq!(
+            paths(&[]),
+            vec![(0, "".to_owned()), (0, "garden".to_owned())]
+        );
+        assert_eq!(
+            paths(&["", "/home"]),
+            vec![
+                (0, "".to_owned()),
+                (1, "/home".to_owned()),
+                (2, "/home/hall".to_owned()),
+                (2, "/home/kitchen".to_owned()),
+                (0, "garden".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use chrono::Local;
+use cursive::{
+    Cursive, Printer, Vec2, View,
+    view::{Nameable, Resizable},
+    views::{Dialog, EditView, LinearLayout, ListView, SelectView, TextView},
+};
+
+use crate::{
+    chart::{Series, get_extent, get_series, zoom_range},
+    time_range::{SHORTCUTS, TimeRange, parse_time_bound},
+};
+
+/// Values read per chart, more than a terminal is wide in braille dots.
+const MAX_POINTS: usize = 2000;
+/// Width of the value labels left of the chart.
+const LABEL_WIDTH: usize = 10;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+struct ChartState {
+    table_name: String,
+    topic: String,
+    range: TimeRange,
+    /// Set for ranges without an end, so reloading keeps showing the last `live_span` seconds.
+    live_span: Option<i64>,
+    auto_refresh: bool,
+    /// Bumped whenever auto refresh is toggled, so an older refresh thread stops.
+    refresh_generation: usize,
+}
+
+/// Line chart of a series with value labels on the left and the time range below.
+struct ChartView {
+    series: Series,
+}
+
+impl View for ChartView {
+    fn draw(&self, printer: &Printer) {
+        let width = printer.size.x.saturating_sub(LABEL_WIDTH + 1);
+        let height = printer.size.y.saturating_sub(1);
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        if self.series.points.is_empty() {
+            printer.print(
+                (LABEL_WIDTH + 1, height / 2),
+                "No numeric values in this range.",
+            );
+        } else {
+            self.series
+                .render(width, height)
+                .iter()
+                .enumerate()
+                .for_each(|(row, line)| printer.print((LABEL_WIDTH + 1, row), line));
+
+            let (min, max) = self.series.value_range();
+            [(0, max), (height / 2, (min + max) / 2.0), (height - 1, min)]
+                .iter()
+                .for_each(|(row, value)| {
+                    printer.print((0, *row), &format!("{:>10.2}", value));
+                });
+        }
+
+        let from = TimeRange::format_bound(Some(self.series.from));
+        let to = TimeRange::format_bound(Some(self.series.to));
+        printer.print((LABEL_WIDTH + 1, height), &from);
+        printer.print((printer.size.x.saturating_sub(to.len()), height), &to);
+    }
+
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+}
+
+/// Chart of the numeric values of `topic`, opened from the table screen.
+pub fn draw_chart(s: &mut Cursive, table_name: &str, topic: &str) {
+    let state = Arc::new(Mutex::new(ChartState {
+        table_name: table_name.to_owned(),
+        topic: topic.to_owned(),
+        range: TimeRange::default(),
+        live_span: None,
+        auto_refresh: false,
+        refresh_generation: 0,
+    }));
+    let state_for_zoom_in = state.clone();
+    let state_for_zoom_out = state.clone();
+    let state_for_range = state.clone();
+    let state_for_all = state.clone();
+    let state_for_refresh = state.clone();
+    let state_for_back = state.clone();
+
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new("").with_name("chart_status"))
+                .child(
+                    ChartView {
+                        series: Series::default(),
+                    }
+                    .with_name("chart")
+                    .min_size((60, 16))
+                    .full_screen(),
+                ),
+        )
+        .title(format!("{} in {}", topic, table_name))
+        .button("ZOOM IN", move |s| {
+            zoom_chart(s, state_for_zoom_in.clone(), 0.5)
+        })
+        .button("ZOOM OUT", move |s| {
+            zoom_chart(s, state_for_zoom_out.clone(), 2.0)
+        })
+        .button("RANGE", move |s| {
+            s.add_layer(create_range_dialog(state_for_range.clone()));
+        })
+        .button("ALL", move |s| {
+            if let Ok(mut state) = state_for_all.lock() {
+                state.range = TimeRange::default();
+                state.live_span = None;
+            }
+            reload_chart(s, state_for_all.clone());
+        })
+        .button("AUTO REFRESH", move |s| {
+            toggle_auto_refresh(s, state_for_refresh.clone());
+        })
+        .button("BACK", move |s| {
+            if let Ok(mut state) = state_for_back.lock() {
+                state.auto_refresh = false;
+                state.refresh_generation += 1;
+            }
+            s.pop_layer();
+        }),
+    );
+
+    reload_chart(s, state);
+}
+
+/// Reads the series again and shows it.
+fn reload_chart(s: &mut Cursive, state: Arc<Mutex<ChartState>>) {
+    let Ok(mut state) = state.lock() else {
+        s.add_layer(Dialog::info("Failed to lock mutex."));
+        return;
+    };
+    if let Some(span) = state.live_span {
+        state.range.from = Some(Local::now().timestamp() - span);
+    }
+
+    let status = match get_series(&state.table_name, &state.topic, state.range, MAX_POINTS) {
+        Ok(series) => {
+            let last = series
+                .points
+                .last()
+                .map(|(_, value)| format!("{:.2}", value))
+                .unwrap_or("-".to_owned());
+            let status = format!(
+                "{} points, last value {}. Auto refresh {}.",
+                series.points.len(),
+                last,
+                if state.auto_refresh { "on" } else { "off" }
+            );
+            s.call_on_name("chart", |v: &mut ChartView| v.series = series);
+            status
+        }
+        Err(e) => format!("Error: {}", e),
+    };
+    s.call_on_name("chart_status", |v: &mut TextView| v.set_content(status));
+}
+
+/// Scales the shown time span by `factor` around its middle.
+fn zoom_chart(s: &mut Cursive, state: Arc<Mutex<ChartState>>, factor: f64) {
+    let shown = s.call_on_name("chart", |v: &mut ChartView| (v.series.from, v.series.to));
+    let Some((from, to)) = shown else {
+        return;
+    };
+
+    if let Ok(mut state) = state.lock() {
+        let extent = get_extent(&state.table_name, &state.topic).unwrap_or_default();
+        state.range = zoom_range(from, to, factor, extent);
+        state.live_span = None;
+    }
+    reload_chart(s, state);
+}
+
+fn toggle_auto_refresh(s: &mut Cursive, state: Arc<Mutex<ChartState>>) {
+    let generation = match state.lock() {
+        Ok(mut state) => {
+            state.auto_refresh = !state.auto_refresh;
+            state.refresh_generation += 1;
+            state.auto_refresh.then_some(state.refresh_generation)
+        }
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+    reload_chart(s, state.clone());
+
+    let Some(generation) = generation else {
+        return;
+    };
+    let screen = s.active_screen();
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(REFRESH_INTERVAL);
+            let current = state
+                .lock()
+                .is_ok_and(|state| state.auto_refresh && state.refresh_generation == generation);
+            if !current {
+                break;
+            }
+            let state = state.clone();
+            if sink
+                .send(Box::new(move |s| refresh_if_shown(s, state, screen)))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
+
+/// Reloads the chart, or stops the auto refresh once another screen was picked, for example
+/// with the global `b` key.
+fn refresh_if_shown(s: &mut Cursive, state: Arc<Mutex<ChartState>>, screen: usize) {
+    let active = s.active_screen();
+    if active == screen {
+        reload_chart(s, state);
+        return;
+    }
+
+    if let Ok(mut state) = state.lock() {
+        state.auto_refresh = false;
+        state.refresh_generation += 1;
+    }
+    // The status is only found on the screen of the chart.
+    s.set_screen(screen);
+    reload_chart(s, state);
+    s.set_screen(active);
+}
+
+fn create_range_dialog(state: Arc<Mutex<ChartState>>) -> Dialog {
+    let current = state.lock().map(|state| state.range).unwrap_or_default();
+
+    let shortcuts = SelectView::<String>::new()
+        .with_all_str(SHORTCUTS)
+        .on_submit(|s, shortcut: &String| {
+            if let Ok(time_range) = TimeRange::parse_shortcut(shortcut, Local::now()) {
+                s.call_on_name("chart_from", |v: &mut EditView| {
+                    v.set_content(TimeRange::format_bound(time_range.from));
+                });
+                s.call_on_name("chart_to", |v: &mut EditView| {
+                    v.set_content(TimeRange::format_bound(time_range.to));
+                });
+            }
+        });
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(
+                ListView::new()
+                    .child(
+                        "From: ",
+                        EditView::new()
+                            .content(TimeRange::format_bound(current.from))
+                            .with_name("chart_from")
+                            .min_width(22),
+                    )
+                    .child(
+                        "To: ",
+                        EditView::new()
+                            .content(TimeRange::format_bound(current.to))
+                            .with_name("chart_to"),
+                    ),
+            )
+            .child(shortcuts)
+            .child(TextView::new("").with_name("chart_range_error")),
+    )
+    .title("Chart range")
+    .button("OK", move |s| {
+        let now = Local::now();
+        let mut read = |name: &str| {
+            s.call_on_name(name, |v: &mut EditView| v.get_content().to_string())
+                .unwrap_or_default()
+        };
+        let from = read("chart_from");
+        let to = read("chart_to");
+        let range = parse_time_bound(&from, now).and_then(|from| {
+            Ok(TimeRange {
+                from,
+                to: parse_time_bound(&to, now)?,
+            })
+        });
+
+        match range {
+            Ok(range) => {
+                if let Ok(mut state) = state.lock() {
+                    state.range = range;
+                    // Ranges without an end follow the clock when refreshed.
+                    state.live_span = match range {
+                        TimeRange {
+                            from: Some(from),
+                            to: None,
+                        } => Some(now.timestamp() - from),
+                        _ => None,
+                    };
+                }
+                s.pop_layer();
+                reload_chart(s, state.clone());
+            }
+            Err(e) => {
+                s.call_on_name("chart_range_error", |v: &mut TextView| {
+                    v.set_content(format!("Error: {}", e))
+                });
+            }
+        }
+    })
+    .button("CANCEL", |s| {
+        s.pop_layer();
+    })
+}
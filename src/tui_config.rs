@@ -13,7 +13,7 @@ use cursive::{
     views::{Button, Dialog, DummyView, EditView, LinearLayout, ListView, TextView},
 };
 
-use crate::{cli_args::ARGS, utils::{ServiceKind, SystemDService}};
+use crate::{broker::load_broker_settings, cli_args::ARGS, tui_retention::draw_retention_rules, utils::{ServiceKind, SystemDService}};
 
 #[derive(Clone)]
 enum FieldToUpdate {
@@ -50,7 +50,7 @@ impl FieldToUpdate {
                     (&ARGS.db_path).to_owned()
                 }
             }
-            FieldToUpdate::BrokerIP => (&ARGS.broker_ip).to_owned(),
+            FieldToUpdate::BrokerIP => load_broker_settings().unwrap_or_default().host,
             FieldToUpdate::InstallLocation => "/usr/local/home_automation".to_owned(),
         }
     }
@@ -458,6 +458,15 @@ pub fn draw_config(s: &mut Cursive, main_menu_id: usize) {
                     )
                     .title("Services"),
                 )
+                .child(
+                    Dialog::around(
+                        LinearLayout::horizontal()
+                            .child(Button::new("RULES", draw_retention_rules))
+                            .child(DummyView)
+                            .child(TextView::new("Prune and average old rows to save space.")),
+                    )
+                    .title("Data Retention"),
+                )
                 .child(Button::new("MAIN MENU", move |s| {
                     s.set_screen(main_menu_id);
                 })),
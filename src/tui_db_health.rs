@@ -0,0 +1,213 @@
+use std::thread;
+
+use anyhow::Result;
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Button, Dialog, DummyView, EditView, LinearLayout, ListView, SelectView, TextView},
+};
+
+use crate::db_health::{
+    DbHealth, Maintenance, SchemaObject, create_index, drop_index, get_db_health, run_maintenance,
+};
+
+/// Screen with the schema, sizes and health of the database. It stays empty until
+/// `refresh_db_health` runs, as the integrity check reads the whole file.
+pub fn draw_db_health(s: &mut Cursive, main_menu_id: usize) {
+    let mut buttons = LinearLayout::vertical().child(Button::new("REFRESH", refresh_db_health));
+    for maintenance in Maintenance::ALL {
+        buttons.add_child(Button::new(maintenance.get_title(), move |s| {
+            confirm_maintenance(s, maintenance)
+        }));
+    }
+    let buttons = buttons
+        .child(DummyView)
+        .child(Button::new("ADD INDEX", handle_add_index))
+        .child(Button::new("DROP INDEX", handle_drop_index))
+        .child(DummyView)
+        .child(Button::new("MAIN MENU", move |s| {
+            s.set_screen(main_menu_id);
+        }));
+
+    let objects = SelectView::<SchemaObject>::new()
+        .on_select(show_create_statement)
+        .with_name("db_health_objects")
+        .scrollable()
+        .min_size((70, 10));
+
+    let details = LinearLayout::vertical()
+        .child(Dialog::around(TextView::new("").with_name("db_health_summary")).title("Health"))
+        .child(Dialog::around(objects).title("Schema"))
+        .child(
+            Dialog::around(
+                TextView::new("")
+                    .with_name("db_health_sql")
+                    .scrollable()
+                    .max_height(12),
+            )
+            .title("CREATE statement"),
+        );
+
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::horizontal()
+                .child(buttons)
+                .child(DummyView)
+                .child(details),
+        )
+        .title("DATABASE"),
+    );
+}
+
+/// Reads the schema and health again and shows them. The reading happens on its own thread,
+/// the integrity check and row counts take a while on big files.
+pub fn refresh_db_health(s: &mut Cursive) {
+    s.call_on_name("db_health_summary", |v: &mut TextView| {
+        v.set_content("Checking the database...")
+    });
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        let res = get_db_health();
+        let _ = sink.send(Box::new(move |s| show_db_health(s, res)));
+    });
+}
+
+fn show_db_health(s: &mut Cursive, res: Result<DbHealth>) {
+    let health = match res {
+        Ok(health) => health,
+        Err(e) => {
+            s.call_on_name("db_health_summary", |v: &mut TextView| v.set_content(""));
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            return;
+        }
+    };
+
+    s.call_on_name("db_health_summary", |v: &mut TextView| {
+        v.set_content(health.describe())
+    });
+    s.call_on_name("db_health_objects", |v: &mut SelectView<SchemaObject>| {
+        v.clear();
+        v.add_all(
+            health
+                .objects
+                .iter()
+                .map(|object| (object.describe(), object.clone())),
+        );
+    });
+    if let Some(object) = health.objects.first() {
+        show_create_statement(s, object);
+    }
+}
+
+fn show_create_statement(s: &mut Cursive, object: &SchemaObject) {
+    let sql = match &object.sql {
+        Some(sql) => sql.to_owned(),
+        None => format!(
+            "SQLite created this {} for {} on its own.",
+            object.kind, object.table_name
+        ),
+    };
+    s.call_on_name("db_health_sql", |v: &mut TextView| v.set_content(sql));
+}
+
+fn confirm_maintenance(s: &mut Cursive, maintenance: Maintenance) {
+    s.add_layer(
+        Dialog::text(maintenance.get_description())
+            .title(maintenance.get_title())
+            .button("RUN", move |s| {
+                s.pop_layer();
+                match run_maintenance(maintenance) {
+                    Ok(result) => {
+                        refresh_db_health(s);
+                        s.add_layer(Dialog::info(result));
+                    }
+                    Err(e) => s.add_layer(Dialog::info(format!("Something went wrong {}", e))),
+                }
+            })
+            .dismiss_button("CANCEL"),
+    );
+}
+
+fn selected_object(s: &mut Cursive) -> Option<SchemaObject> {
+    s.call_on_name("db_health_objects", |v: &mut SelectView<SchemaObject>| {
+        v.selection().map(|object| (*object).clone())
+    })
+    .flatten()
+}
+
+fn handle_add_index(s: &mut Cursive) {
+    let table_name = selected_object(s)
+        .filter(|object| object.kind != "view")
+        .map(|object| object.table_name)
+        .unwrap_or_default();
+
+    s.add_layer(
+        Dialog::around(
+            ListView::new()
+                .child(
+                    "Table: ",
+                    EditView::new()
+                        .content(table_name)
+                        .with_name("index_table")
+                        .min_width(30),
+                )
+                .child(
+                    "Columns: ",
+                    EditView::new()
+                        .content("topic, timestamp")
+                        .with_name("index_columns"),
+                )
+                .child("Name: ", EditView::new().with_name("index_name")),
+        )
+        .title("Add index")
+        .button("ADD", |s| {
+            let read = |s: &mut Cursive, name: &str| {
+                s.call_on_name(name, |v: &mut EditView| v.get_content())
+                    .map(|content| content.trim().to_owned())
+                    .unwrap_or_default()
+            };
+            let table_name = read(s, "index_table");
+            let columns: Vec<String> = read(s, "index_columns")
+                .split(',')
+                .map(|column| column.trim().to_owned())
+                .filter(|column| !column.is_empty())
+                .collect();
+            let name = match read(s, "index_name") {
+                name if name.is_empty() => format!("{}_{}", table_name, columns.join("_")),
+                name => name,
+            };
+
+            match create_index(&name, &table_name, &columns) {
+                Ok(()) => {
+                    s.pop_layer();
+                    refresh_db_health(s);
+                }
+                Err(e) => s.add_layer(Dialog::info(format!("Something went wrong {}", e))),
+            }
+        })
+        .dismiss_button("CANCEL"),
+    );
+}
+
+fn handle_drop_index(s: &mut Cursive) {
+    let Some(index) = selected_object(s).filter(|object| object.kind == "index") else {
+        s.add_layer(Dialog::info("Select an index first."));
+        return;
+    };
+
+    s.add_layer(
+        Dialog::text(format!(
+            "Drop the index {} of {}?",
+            index.name, index.table_name
+        ))
+        .title("Drop index")
+        .button("DROP", move |s| {
+            s.pop_layer();
+            match drop_index(&index.name) {
+                Ok(()) => refresh_db_health(s),
+                Err(e) => s.add_layer(Dialog::info(format!("Something went wrong {}", e))),
+            }
+        })
+        .dismiss_button("CANCEL"),
+    );
+}
@@ -1,7 +1,8 @@
 use std::{
+    io::Error,
     sync::{Arc, Mutex},
     thread::{self},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -10,20 +11,48 @@ use chrono::{Local, Utc};
 use cursive::{
     Cursive,
     theme::{BaseColor, Color, ColorStyle, Effect, Style},
+    utils::markup::StyledString,
     view::Nameable,
+    view::{Resizable, Scrollable},
     views::{
-        Button, Dialog, EditView, LinearLayout, NamedView, OnEventView, ScrollView, SelectView,
-        TextView,
+        Button, Checkbox, Dialog, EditView, LinearLayout, ListView, NamedView, OnEventView,
+        ScrollView, SelectView, TextArea, TextView,
     },
 };
 use mosquitto_rs::{Client, Event, QoS};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 
-use crate::cli_args::ARGS;
+use crate::{
+    broker::{BrokerSettings, load_broker_settings, save_broker_settings},
+    cli_args::ARGS,
+    connection::{Backoff, ConnectionReport, ConnectionState, is_auth_failure},
+    profiles::{Profile, load_profiles, save_profiles, upsert_profile},
+    publish::{
+        PublishRequest, add_to_history, load_history, qos_from_level, qos_level, save_history,
+        validate_request,
+    },
+    subscriptions::{
+        COLORS, Subscription, find_subscription, load_subscriptions, save_subscriptions,
+        validate_filter,
+    },
+};
+
+/// A line of the LOGS screen. Messages carry the color of the subscription they matched.
+struct LogLine {
+    text: String,
+    color: Option<Color>,
+}
+
+impl From<String> for LogLine {
+    fn from(text: String) -> Self {
+        LogLine { text, color: None }
+    }
+}
 
 async fn receive_messages(
     async_channel_receiver: Receiver<Event>,
-    sender: UnboundedSender<String>,
+    sender: UnboundedSender<LogLine>,
+    subscriptions: &[Subscription],
 ) -> Result<()> {
     loop {
         let res = async_channel_receiver.recv().await?;
@@ -32,16 +61,22 @@ async fn receive_messages(
                 let msg_str = String::from_utf8(message.payload)
                     .unwrap_or_else(|e| format!("Failed to parse string: {}", e));
 
-                let new_msg = format!("{}: {}", message.topic, msg_str);
+                let new_msg = match find_subscription(subscriptions, &message.topic) {
+                    Some(subscription) => LogLine {
+                        text: format!("[{}] {}: {}", subscription.filter, message.topic, msg_str),
+                        color: Some(subscription.get_color()),
+                    },
+                    None => format!("{}: {}", message.topic, msg_str).into(),
+                };
                 sender.send(new_msg)?;
             }
             Event::Connected(connection_status) => {
                 let new_msg = format!("MQTT Connected Event: {}", connection_status);
-                sender.send(new_msg)?;
+                sender.send(new_msg.into())?;
             }
             Event::Disconnected(reason_code) => {
                 let new_msg = format!("Disconnected: {}", reason_code);
-                sender.send(new_msg)?;
+                sender.send(new_msg.into())?;
                 return Ok(());
             }
         }
@@ -49,118 +84,263 @@ async fn receive_messages(
 }
 
 enum UIEvent {
-    UpdateTopic(String),
-    UpdateHost(String),
+    UpdateSubscriptions(Vec<Subscription>),
+    UpdateBroker(BrokerSettings),
+    SwitchProfile(Profile),
 }
 
 struct UIState {
-    topic: String,
-    host: String,
+    subscriptions: Vec<Subscription>,
+    broker: BrokerSettings,
 }
 
 async fn log_collection_async(
-    log_sender: UnboundedSender<String>,
-    done_receiver: UnboundedReceiver<bool>,
+    broker: BrokerSettings,
+    log_sender: UnboundedSender<LogLine>,
+    status_sender: UnboundedSender<ConnectionReport>,
+    mut done_receiver: UnboundedReceiver<bool>,
     mut ui_event_receiver: UnboundedReceiver<UIEvent>,
+    mut publish_receiver: UnboundedReceiver<PublishRequest>,
+    connected: Arc<Mutex<bool>>,
 ) -> Result<()> {
-    let done_receiver = Arc::new(Mutex::new(done_receiver));
     // UI state can live here.
     let state = Arc::new(Mutex::new(UIState {
-        topic: (&ARGS.topic).to_owned(),
-        host: (&ARGS.broker_ip).to_owned(),
+        subscriptions: vec![],
+        broker,
     }));
 
     while let Some(ui_event) = ui_event_receiver.recv().await {
         let state_cp = state.clone();
-        let client = Client::with_auto_id()?;
+        apply_ui_event(&state_cp, ui_event);
 
-        match ui_event {
-            UIEvent::UpdateTopic(new_topic) => {
-                if let Ok(mut state) = state_cp.lock() {
-                    (*state).topic = new_topic;
-                };
-            }
-            UIEvent::UpdateHost(new_host) => {
-                if let Ok(mut state) = state_cp.lock() {
-                    (*state).host = new_host;
-                };
-            }
+        // Signals sent with this event, or with events applied below, would end the next
+        // connection right away.
+        while done_receiver.try_recv().is_ok() {}
+        while let Ok(ui_event) = ui_event_receiver.try_recv() {
+            apply_ui_event(&state_cp, ui_event);
         }
 
-        let host;
-        let topic;
+        let broker;
+        let subscriptions;
         // Creating a scope here and reading the host of state to avoid locking up the
         // state for too long.
         {
             let lock = state_cp.lock();
             match lock {
                 Ok(ref state) => {
-                    host = (*state).host.to_owned();
-                    topic = (*state).topic.to_owned();
+                    broker = state.broker.clone();
+                    subscriptions = state.subscriptions.clone();
                 }
                 Err(_) => {
-                    host = "localhost".to_owned();
-                    topic = "/#".to_owned();
+                    broker = BrokerSettings::default();
+                    subscriptions = vec![];
                 }
             }
         }
 
-        if let Ok(e) = client
-            .connect(&host, 1883, Duration::from_secs(5), None)
-            .await
-        {
-            log_sender.send(format!("{}", e))?;
-        };
+        // Reconnects until the UI picks new settings or the broker refuses the credentials.
+        let mut backoff = Backoff::default();
+        let mut drops = 0;
+        loop {
+            let report = |state| ConnectionReport { state, drops };
+            let _ = status_sender.send(report(ConnectionState::Connecting));
 
-        let done_receiver_cp = done_receiver.clone();
-        let log_sender = log_sender.clone();
-        if let Err(e) = client.subscribe(&topic, QoS::AtMostOnce).await {
-            log_sender.send(format!("Err Subscribing: {}", e))?;
-        };
+            let delay = match connect_and_subscribe(&broker, &subscriptions, &log_sender).await {
+                Ok(client) => {
+                    let _ = status_sender.send(report(ConnectionState::Connected));
+                    if let Some(subscriber_receiver) = client.subscriber() {
+                        let connected_at = Instant::now();
+                        set_connected(&connected, true);
+                        let done = race_done_receiver(
+                            log_sender.clone(),
+                            &mut done_receiver,
+                            subscriber_receiver,
+                            &client,
+                            &subscriptions,
+                            &mut publish_receiver,
+                        )
+                        .await;
+                        set_connected(&connected, false);
+                        drop_publishes(&mut publish_receiver, &log_sender);
+                        if done {
+                            break;
+                        }
+                        backoff.reset_if_held(connected_at.elapsed());
+                        drops += 1;
+                    } else {
+                        log_sender.send("No sub found...".to_owned().into())?;
+                    }
+                    backoff.next_delay()
+                }
+                Err(e) => {
+                    log_sender
+                        .send(format!("Err Connecting to {}: {}", broker.describe(), e).into())?;
+                    if is_auth_failure(&e) {
+                        let _ = status_sender.send(report(ConnectionState::AuthFailed));
+                        break;
+                    }
+                    backoff.next_delay()
+                }
+            };
 
-        let subscriber = client.subscriber();
-        if let Some(subscriber_receiver) = subscriber {
-            race_done_receiver(log_sender, done_receiver_cp, subscriber_receiver).await;
-        } else {
-            log_sender.send("No sub found...".to_owned())?;
+            let _ = status_sender.send(ConnectionReport {
+                state: ConnectionState::Retrying(delay),
+                drops,
+            });
+            log_sender.send(format!("Reconnecting in {}s.", delay.as_secs()).into())?;
+            if wait_for_retry(&mut done_receiver, delay).await {
+                break;
+            }
         }
     }
 
     Ok(())
 }
 
+/// Lets the PUBLISH form hand requests over, it refuses them while this is false.
+fn set_connected(connected: &Mutex<bool>, value: bool) {
+    if let Ok(mut connected) = connected.lock() {
+        *connected = value;
+    }
+}
+
+/// Logs and forgets the publishes the connection ended before, so that they do not go out
+/// late or to another broker.
+fn drop_publishes(
+    publish_receiver: &mut UnboundedReceiver<PublishRequest>,
+    log_sender: &UnboundedSender<LogLine>,
+) {
+    while let Ok(request) = publish_receiver.try_recv() {
+        let _ = log_sender.send(
+            format!(
+                "Err Publishing to {}: the connection ended first",
+                request.topic
+            )
+            .into(),
+        );
+    }
+}
+
+fn apply_ui_event(state: &Mutex<UIState>, ui_event: UIEvent) {
+    let Ok(mut state) = state.lock() else {
+        return;
+    };
+    match ui_event {
+        UIEvent::UpdateSubscriptions(new_subscriptions) => {
+            state.subscriptions = new_subscriptions;
+        }
+        UIEvent::UpdateBroker(new_broker) => {
+            state.broker = new_broker;
+        }
+        UIEvent::SwitchProfile(profile) => {
+            state.broker = profile.broker;
+            if !profile.subscriptions.is_empty() {
+                state.subscriptions = profile.subscriptions;
+            }
+        }
+    }
+}
+
+/// Connects a new client to `broker` and subscribes to the enabled `subscriptions`. Failed
+/// subscriptions are logged without failing the connection.
+async fn connect_and_subscribe(
+    broker: &BrokerSettings,
+    subscriptions: &[Subscription],
+    log_sender: &UnboundedSender<LogLine>,
+) -> Result<Client> {
+    let client = broker.create_client()?;
+    let status = broker.connect(&client).await?;
+    log_sender.send(format!("{}", status).into())?;
+
+    let enabled: Vec<&Subscription> = subscriptions
+        .iter()
+        .filter(|subscription| subscription.enabled)
+        .collect();
+    if enabled.is_empty() {
+        log_sender.send("No subscriptions enabled.".to_owned().into())?;
+    }
+    for subscription in enabled {
+        if let Err(e) = client
+            .subscribe(&subscription.filter, subscription.qos)
+            .await
+        {
+            log_sender.send(format!("Err Subscribing to {}: {}", subscription.filter, e).into())?;
+        };
+    }
+    Ok(client)
+}
+
+/// Sleeps for `delay`. Returns true when the UI picks new settings meanwhile.
+async fn wait_for_retry(done_receiver: &mut UnboundedReceiver<bool>, delay: Duration) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => false,
+        _ = done_receiver.recv() => true,
+    }
+}
+
+/// Logs messages until the broker disconnects or the broker or subscriptions change.
+/// Publishes from the PUBLISH form go out through `client` meanwhile. Returns true when the
+/// UI asked to stop, false when the connection dropped.
 async fn race_done_receiver(
-    log_sender: UnboundedSender<String>,
-    done_receiver: Arc<Mutex<UnboundedReceiver<bool>>>,
+    log_sender: UnboundedSender<LogLine>,
+    done_receiver: &mut UnboundedReceiver<bool>,
     subscriber_receiver: Receiver<Event>,
-) {
+    client: &Client,
+    subscriptions: &[Subscription],
+    publish_receiver: &mut UnboundedReceiver<PublishRequest>,
+) -> bool {
     let subscriber_receiver = subscriber_receiver.clone();
     let subscriber_receiver_cp = subscriber_receiver.clone();
 
     let sender_cp = log_sender.clone();
     let sender_cp_cp = log_sender.clone();
 
-    if let Ok(mut done_receiver) = done_receiver.lock() {
+    let messages = receive_messages(subscriber_receiver, sender_cp_cp, subscriptions);
+    tokio::pin!(messages);
+    loop {
         tokio::select! {
             msg = done_receiver.recv() => {
-                if let Some(_) = msg {
-                    let _ = sender_cp.send("Done".to_owned());
+                if msg.is_some() {
+                    let _ = sender_cp.send("Done".to_owned().into());
                     subscriber_receiver_cp.close();
                 }
+                return true;
             }
-            _ = receive_messages(subscriber_receiver, sender_cp_cp) => {
-                let _ = log_sender.send("Got msg".to_owned());
+            _ = &mut messages => {
+                let _ = log_sender.send("Got msg".to_owned().into());
+                return false;
+            }
+            Some(request) = publish_receiver.recv() => {
+                publish(client, &request, &log_sender).await;
             }
         }
-    } else {
-        let _ = log_sender.send("Failed to lock mutex on done race.".to_owned());
     }
 }
 
+async fn publish(client: &Client, request: &PublishRequest, log_sender: &UnboundedSender<LogLine>) {
+    let msg = match client
+        .publish(
+            &request.topic,
+            &request.payload,
+            request.qos,
+            request.retain,
+        )
+        .await
+    {
+        Ok(_) => format!("Published to {}: {}", request.topic, request.payload),
+        Err(e) => format!("Err Publishing: {}", e),
+    };
+    let _ = log_sender.send(msg.into());
+}
+
 fn spawn_data_collection_thread(
-    log_sender: UnboundedSender<String>,
+    broker: BrokerSettings,
+    log_sender: UnboundedSender<LogLine>,
+    status_sender: UnboundedSender<ConnectionReport>,
     done_receiver: UnboundedReceiver<bool>,
     ui_event_receiver: UnboundedReceiver<UIEvent>,
+    publish_receiver: UnboundedReceiver<PublishRequest>,
+    connected: Arc<Mutex<bool>>,
 ) {
     thread::spawn(|| {
         let rt = tokio::runtime::Builder::new_current_thread()
@@ -169,74 +349,180 @@ fn spawn_data_collection_thread(
 
         if let Ok(rt) = rt {
             let _: Result<()> = rt.block_on(async move {
-                log_collection_async(log_sender, done_receiver, ui_event_receiver).await
+                log_collection_async(
+                    broker,
+                    log_sender,
+                    status_sender,
+                    done_receiver,
+                    ui_event_receiver,
+                    publish_receiver,
+                    connected,
+                )
+                .await
             });
         }
     });
 }
 
-fn spawn_log_receiver_thread(s: &mut Cursive, mut log_receiver: UnboundedReceiver<String>) {
+fn spawn_log_receiver_thread(s: &mut Cursive, mut log_receiver: UnboundedReceiver<LogLine>) {
     let sink = s.cb_sink().clone();
     thread::spawn(move || {
         while let Some(msg) = log_receiver.blocking_recv() {
+            let label = match msg.color {
+                Some(color) => StyledString::styled(timestamped(&msg.text), color),
+                None => StyledString::plain(timestamped(&msg.text)),
+            };
             let _ = sink.send(Box::new(move |s| {
                 s.call_on_name("logs_view", |v: &mut SelectView| {
                     // Really expensive, but I like the items coming in at the top, because the
                     // newest is always visible then.
-                    v.insert_item(
-                        0,
-                        Local::now()
-                            .naive_local()
-                            .format("%Y/%m/%d %H:%M:%S")
-                            .to_string()
-                            + "-> "
-                            + &msg,
-                        Utc::now().to_rfc2822(),
-                    );
+                    v.insert_item(0, label, Utc::now().to_rfc2822());
                 });
             }));
         }
     });
 }
 
+fn spawn_status_receiver_thread(
+    s: &mut Cursive,
+    mut status_receiver: UnboundedReceiver<ConnectionReport>,
+) {
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        while let Some(report) = status_receiver.blocking_recv() {
+            let _ = sink.send(Box::new(move |s| {
+                s.call_on_name("connection_status", |v: &mut TextView| {
+                    v.set_content(report.describe());
+                });
+            }));
+        }
+    });
+}
+
+fn timestamped(msg: &str) -> String {
+    Local::now()
+        .naive_local()
+        .format("%Y/%m/%d %H:%M:%S")
+        .to_string()
+        + "-> "
+        + msg
+}
+
+/// Subscribes to `ARGS.topic` alone on the broker of `load_broker_settings` and prints every
+/// message to stdout. Reconnects when the connection drops, returns once the broker refuses
+/// the credentials.
+pub fn print_logs() -> Result<()> {
+    let broker = load_broker_settings()?;
+    let (log_sender, mut log_receiver) = mpsc::unbounded_channel::<LogLine>();
+    // The done sender is held until we return, a dropped sender would end the subscription.
+    let (_done_sender, done_receiver) = mpsc::unbounded_channel::<bool>();
+    let (topic_sender, topic_receiver) = mpsc::unbounded_channel::<UIEvent>();
+    // Nothing is published from the command line, and the log has the connection state.
+    let (_, publish_receiver) = mpsc::unbounded_channel::<PublishRequest>();
+    let (status_sender, _) = mpsc::unbounded_channel::<ConnectionReport>();
+
+    spawn_data_collection_thread(
+        broker,
+        log_sender,
+        status_sender,
+        done_receiver,
+        topic_receiver,
+        publish_receiver,
+        Arc::new(Mutex::new(false)),
+    );
+
+    topic_sender.send(UIEvent::UpdateSubscriptions(vec![Subscription::new(
+        &ARGS.topic,
+    )]))?;
+    // Dropping the event sender lets the collection loop finish once it stops reconnecting.
+    drop(topic_sender);
+
+    while let Some(msg) = log_receiver.blocking_recv() {
+        println!("{}", timestamped(&msg.text));
+    }
+
+    Ok(())
+}
+
 pub fn draw_logs(s: &mut Cursive, main_menu_id: usize) {
     s.pop_layer();
-    if let Some(_) = s.call_on_name("logs_view", |_v: &mut NamedView<SelectView>| {}) {
+    if s.call_on_name("logs_view", |_v: &mut NamedView<SelectView>| {})
+        .is_some()
+    {
         s.pop_layer();
     };
 
-    let (log_sender, log_receiver) = mpsc::unbounded_channel::<String>();
+    let (log_sender, log_receiver) = mpsc::unbounded_channel::<LogLine>();
     let (done_sender, done_receiver) = mpsc::unbounded_channel::<bool>();
     let (topic_sender, topic_receiver) = mpsc::unbounded_channel::<UIEvent>();
+    let (publish_sender, publish_receiver) = mpsc::unbounded_channel::<PublishRequest>();
+    let (status_sender, status_receiver) = mpsc::unbounded_channel::<ConnectionReport>();
+    let connected = Arc::new(Mutex::new(false));
+
+    let (broker, load_error) = match load_broker_settings() {
+        Ok(broker) => (broker, None),
+        Err(e) => (BrokerSettings::default(), Some(e)),
+    };
+    let current_broker = Arc::new(Mutex::new(broker.clone()));
+    let (subscriptions, subscriptions_error) = match load_subscriptions() {
+        Ok(subscriptions) => (subscriptions, None),
+        Err(e) => (vec![Subscription::new(&ARGS.topic)], Some(e)),
+    };
+    let current_subscriptions = Arc::new(Mutex::new(subscriptions.clone()));
 
     // This thread is responsible for connecting and reconnecting to the mosquitto instance.
-    spawn_data_collection_thread(log_sender, done_receiver, topic_receiver);
+    spawn_data_collection_thread(
+        broker.clone(),
+        log_sender,
+        status_sender,
+        done_receiver,
+        topic_receiver,
+        publish_receiver,
+        connected.clone(),
+    );
 
     // This thread is responsible for receiving logs from mosquitto, and
     // Cursive reference is added here for the Cursive CB sink to update the UI
     // based on the messages from the log receiver.
     spawn_log_receiver_thread(s, log_receiver);
+    spawn_status_receiver_thread(s, status_receiver);
 
     let done_sender_cp = done_sender.clone();
     let done_sender_cp_cp = done_sender.clone();
     let topic_sender_cp = topic_sender.clone();
     let topic_sender_cp_cp = topic_sender.clone();
+    let profiles_view = ProfilesDialogCreator::new(
+        current_broker.clone(),
+        current_subscriptions.clone(),
+        topic_sender.clone(),
+        done_sender.clone(),
+    );
 
     let buttons = LinearLayout::vertical()
-        .child(Button::new("EDIT HOST", move |s| {
-            let event_sender1 = topic_sender_cp.clone();
-            let done_sender1 = done_sender_cp.clone();
-            let view =
-                EditFieldDialogCreator::new(event_sender1, done_sender1, FieldToUpdate::Host);
-            s.add_layer(view.create_view());
+        .child(Button::new("EDIT BROKER", move |s| {
+            s.add_layer(create_broker_dialog(
+                current_broker.clone(),
+                topic_sender_cp.clone(),
+                done_sender_cp.clone(),
+            ));
         }))
-        .child(Button::new("EDIT TOPIC", move |s| {
-            let event_sender1 = topic_sender_cp_cp.clone();
-            let done_sender1 = done_sender_cp_cp.clone();
-            let view =
-                EditFieldDialogCreator::new(event_sender1, done_sender1, FieldToUpdate::Topic);
+        .child(Button::new("PROFILES", move |s| {
+            s.add_layer(profiles_view.clone().create_view());
+        }))
+        .child(Button::new("SUBSCRIPTIONS", move |s| {
+            let view = SubscriptionsDialogCreator::new(
+                current_subscriptions.clone(),
+                topic_sender_cp_cp.clone(),
+                done_sender_cp_cp.clone(),
+            );
             s.add_layer(view.create_view());
         }))
+        .child(Button::new("PUBLISH", move |s| {
+            s.add_layer(create_publish_dialog(
+                publish_sender.clone(),
+                connected.clone(),
+            ));
+        }))
         .child(Button::new("CLEAR LOG", |s| {
             s.call_on_name("logs_view", |v: &mut SelectView| {
                 v.clear();
@@ -257,26 +543,41 @@ pub fn draw_logs(s: &mut Cursive, main_menu_id: usize) {
                             Color::Dark(BaseColor::White),
                         ))),
                 )
-                .child(TextView::new(&ARGS.broker_ip).with_name("current_host")),
+                .child(TextView::new(broker.describe()).with_name("current_host")),
         )
         .child(
             LinearLayout::horizontal()
                 .child(
-                    TextView::new("Current topic: ")
+                    TextView::new("Subscriptions: ")
                         .style(Style::from(Effect::Bold))
                         .style(Style::from(ColorStyle::new(
                             Color::Dark(BaseColor::Black),
                             Color::Dark(BaseColor::White),
                         ))),
                 )
-                .child(TextView::new(&ARGS.topic).with_name("current_topic")),
+                .child(
+                    TextView::new(describe_enabled(&subscriptions))
+                        .with_name("current_subscriptions"),
+                ),
+        )
+        .child(
+            LinearLayout::horizontal()
+                .child(
+                    TextView::new("Connection:    ")
+                        .style(Style::from(Effect::Bold))
+                        .style(Style::from(ColorStyle::new(
+                            Color::Dark(BaseColor::Black),
+                            Color::Dark(BaseColor::White),
+                        ))),
+                )
+                .child(TextView::new("connecting").with_name("connection_status")),
         );
 
     let form = LinearLayout::horizontal().child(buttons).child(labels);
 
     // Initial message. Both topic sender and done sender are consumed by this step.
-    if let Err(e) = topic_sender.send(UIEvent::UpdateTopic((&ARGS.topic).to_owned())) {
-        s.add_layer(Dialog::info(&format!("{:?}", e)));
+    if let Err(e) = topic_sender.send(UIEvent::UpdateSubscriptions(subscriptions)) {
+        s.add_layer(Dialog::info(format!("{:?}", e)));
     };
     let logs_view = Dialog::around(ScrollView::new(
         OnEventView::new(SelectView::<String>::new().with_name("logs_view")).on_event(
@@ -289,87 +590,656 @@ pub fn draw_logs(s: &mut Cursive, main_menu_id: usize) {
 
     let container = LinearLayout::vertical().child(form).child(logs_view);
 
-    s.add_layer(container)
+    s.add_layer(container);
+    if let Some(e) = load_error {
+        s.add_layer(Dialog::info(format!(
+            "Could not read the broker settings, using the defaults: {}",
+            e
+        )));
+    }
+    if let Some(e) = subscriptions_error {
+        s.add_layer(Dialog::info(format!(
+            "Could not read the subscriptions, using {}: {}",
+            ARGS.topic, e
+        )));
+    }
+}
+
+/// Form with every broker setting. CONNECT drops the current connection and connects with
+/// the new settings, saving them first when asked to.
+fn create_broker_dialog(
+    current_broker: Arc<Mutex<BrokerSettings>>,
+    event_sender: UnboundedSender<UIEvent>,
+    done_sender: UnboundedSender<bool>,
+) -> Dialog {
+    let broker = current_broker
+        .lock()
+        .map(|broker| broker.clone())
+        .unwrap_or_default();
+    let field = |name: &str, value: &str| EditView::new().content(value).with_name(name);
+
+    let form = ListView::new()
+        .child("Host: ", field("broker_host", &broker.host).min_width(40))
+        .child("Port: ", field("broker_port", &broker.port.to_string()))
+        .child("Username: ", field("broker_username", &broker.username))
+        .child(
+            "Password: ",
+            EditView::new()
+                .secret()
+                .content(&broker.password)
+                .with_name("broker_password"),
+        )
+        .child("Client id: ", field("broker_client_id", &broker.client_id))
+        .child(
+            "Keepalive (s): ",
+            field("broker_keepalive", &broker.keepalive.to_string()),
+        )
+        .child(
+            "TLS: ",
+            Checkbox::new()
+                .with_checked(broker.tls)
+                .with_name("broker_tls"),
+        )
+        .child("CA file: ", field("broker_ca_file", &broker.ca_file))
+        .child("Cert file: ", field("broker_cert_file", &broker.cert_file))
+        .child("Key file: ", field("broker_key_file", &broker.key_file))
+        .child(
+            "Remember: ",
+            Checkbox::new().checked().with_name("broker_remember"),
+        );
+
+    Dialog::around(form)
+        .title("Broker")
+        .button("CONNECT", move |s| {
+            let broker = match read_broker_form(s) {
+                Ok(broker) => broker,
+                Err(e) => {
+                    s.add_layer(Dialog::info(format!("{}", e)));
+                    return;
+                }
+            };
+            let remember = s
+                .call_on_name("broker_remember", |v: &mut Checkbox| v.is_checked())
+                .unwrap_or_default();
+            if remember && let Err(e) = save_broker_settings(&broker) {
+                s.add_layer(Dialog::info(format!("Could not save the settings: {}", e)));
+                return;
+            }
+
+            if let Err(e) = done_sender.send(true) {
+                s.add_layer(Dialog::info(format!("{}", e)));
+                return;
+            }
+            if let Err(e) = event_sender.send(UIEvent::UpdateBroker(broker.clone())) {
+                s.add_layer(Dialog::info(format!("{}", e)));
+                return;
+            }
+            s.call_on_name("current_host", |v: &mut TextView| {
+                v.set_content(broker.describe())
+            });
+            if let Ok(mut current_broker) = current_broker.lock() {
+                *current_broker = broker;
+            }
+            s.pop_layer();
+        })
+        .button("CANCEL", |s| {
+            s.pop_layer();
+        })
 }
 
+fn read_broker_form(s: &mut Cursive) -> Result<BrokerSettings> {
+    let mut read = |name: &str| {
+        s.call_on_name(name, |v: &mut EditView| v.get_content().trim().to_owned())
+            .unwrap_or_default()
+    };
+    let mut content = [
+        "host",
+        "port",
+        "username",
+        "password",
+        "client_id",
+        "keepalive",
+        "ca_file",
+        "cert_file",
+        "key_file",
+    ]
+    .iter()
+    .map(|key| format!("{} = {}", key, read(&format!("broker_{}", key))))
+    .collect::<Vec<String>>();
+    let tls = s
+        .call_on_name("broker_tls", |v: &mut Checkbox| v.is_checked())
+        .unwrap_or_default();
+    content.push(format!("tls = {}", tls));
+
+    let broker = BrokerSettings::parse(&content.join("\n"))?;
+    if broker.keepalive < 5 {
+        return Err(Error::other("The keepalive has to be at least 5 seconds.").into());
+    }
+    Ok(broker)
+}
+
+/// Form to publish a message, with the earlier publishes to resend below it. Publishes are
+/// only accepted while `connected`.
+fn create_publish_dialog(
+    publish_sender: UnboundedSender<PublishRequest>,
+    connected: Arc<Mutex<bool>>,
+) -> Dialog {
+    let history = Arc::new(Mutex::new(load_history()));
+    let history_for_resend = history.clone();
+    let publish_sender_for_resend = publish_sender.clone();
+    let connected_for_resend = connected.clone();
+
+    let form = ListView::new()
+        .child(
+            "Topic: ",
+            EditView::new().with_name("publish_topic").min_width(40),
+        )
+        .child(
+            "Payload: ",
+            TextArea::new().with_name("publish_payload").min_height(3),
+        )
+        .child("QoS: ", qos_select().with_name("publish_qos"))
+        .child("Retain: ", Checkbox::new().with_name("publish_retain"));
+
+    let mut history_view = SelectView::<PublishRequest>::new();
+    if let Ok(history) = history.lock() {
+        add_publish_history(&mut history_view, &history);
+    }
+    let history_view = history_view
+        .on_select(fill_publish_form)
+        // Resending is a single ENTER on an earlier publish.
+        .on_submit(move |s, request: &PublishRequest| {
+            send_publish(
+                s,
+                request.clone(),
+                &publish_sender_for_resend,
+                &connected_for_resend,
+                &history_for_resend,
+            );
+        })
+        .with_name("publish_history")
+        .scrollable()
+        .max_height(10);
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(form)
+            .child(Dialog::around(history_view).title("History, ENTER to resend")),
+    )
+    .title("Publish")
+    .button("SEND", move |s| {
+        let Some(request) = read_publish_form(s) else {
+            return;
+        };
+        send_publish(s, request, &publish_sender, &connected, &history);
+    })
+    .button("CLOSE", |s| {
+        s.pop_layer();
+    })
+}
+
+/// Newest first, same as the logs.
+fn add_publish_history(v: &mut SelectView<PublishRequest>, history: &[PublishRequest]) {
+    v.add_all(
+        history
+            .iter()
+            .rev()
+            .map(|request| (request.describe(), request.clone())),
+    );
+}
+
+fn read_publish_form(s: &mut Cursive) -> Option<PublishRequest> {
+    let request = PublishRequest {
+        topic: s
+            .call_on_name("publish_topic", |v: &mut EditView| {
+                v.get_content().trim().to_owned()
+            })
+            .unwrap_or_default(),
+        payload: s
+            .call_on_name("publish_payload", |v: &mut TextArea| {
+                v.get_content().to_owned()
+            })
+            .unwrap_or_default(),
+        qos: s
+            .call_on_name("publish_qos", |v: &mut SelectView<u64>| {
+                v.selection().and_then(|level| qos_from_level(*level))
+            })
+            .flatten()
+            .unwrap_or(QoS::AtMostOnce),
+        retain: s
+            .call_on_name("publish_retain", |v: &mut Checkbox| v.is_checked())
+            .unwrap_or_default(),
+    };
+
+    match validate_request(&request) {
+        Ok(()) => Some(request),
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("{}", e)));
+            None
+        }
+    }
+}
+
+fn fill_publish_form(s: &mut Cursive, request: &PublishRequest) {
+    s.call_on_name("publish_topic", |v: &mut EditView| {
+        v.set_content(request.topic.to_owned());
+    });
+    s.call_on_name("publish_payload", |v: &mut TextArea| {
+        v.set_content(request.payload.to_owned());
+    });
+    s.call_on_name("publish_qos", |v: &mut SelectView<u64>| {
+        v.set_selection(qos_level(request.qos) as usize);
+    });
+    s.call_on_name("publish_retain", |v: &mut Checkbox| {
+        v.set_checked(request.retain);
+    });
+}
+
+/// Hands `request` to the collection thread and moves it to the top of the history. Nothing
+/// is queued while disconnected, it would go out late or to the next broker.
+fn send_publish(
+    s: &mut Cursive,
+    request: PublishRequest,
+    publish_sender: &UnboundedSender<PublishRequest>,
+    connected: &Mutex<bool>,
+    history: &Arc<Mutex<Vec<PublishRequest>>>,
+) {
+    // Held while sending, so the collection thread drops the request if the connection ends.
+    let Ok(connected) = connected.lock() else {
+        s.add_layer(Dialog::info("Failed to lock mutex."));
+        return;
+    };
+    if !*connected {
+        s.add_layer(Dialog::info("Not connected, nothing was published."));
+        return;
+    }
+    if let Err(e) = publish_sender.send(request.clone()) {
+        s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+        return;
+    }
+    drop(connected);
+
+    let Ok(mut history) = history.lock() else {
+        return;
+    };
+    add_to_history(&mut history, &request);
+    if let Err(e) = save_history(&history) {
+        s.add_layer(Dialog::info(format!(
+            "Could not save publish history: {}",
+            e
+        )));
+    }
+    s.call_on_name("publish_history", |v: &mut SelectView<PublishRequest>| {
+        v.clear();
+        add_publish_history(v, &history);
+    });
+}
+
+fn qos_select() -> SelectView<u64> {
+    let mut qos = SelectView::<u64>::new().popup();
+    qos.add_all([
+        ("0 at most once", 0),
+        ("1 at least once", 1),
+        ("2 exactly once", 2),
+    ]);
+    qos
+}
+
+/// Enabled filters for the label above the logs.
+fn describe_enabled(subscriptions: &[Subscription]) -> String {
+    let enabled: Vec<&str> = subscriptions
+        .iter()
+        .filter(|subscription| subscription.enabled)
+        .map(|subscription| subscription.filter.as_str())
+        .collect();
+    match enabled.is_empty() {
+        true => "none".to_owned(),
+        false => enabled.join(", "),
+    }
+}
+
+/// Dialogs editing the subscriptions of the LOGS screen. Every change is saved and
+/// resubscribes right away.
 #[derive(Clone)]
-enum FieldToUpdate {
-    Topic,
-    Host,
+struct SubscriptionsDialogCreator {
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+    event_sender: UnboundedSender<UIEvent>,
+    done_sender: UnboundedSender<bool>,
 }
 
-impl FieldToUpdate {
-    fn into_element_name(&self) -> &str {
-        match self {
-            FieldToUpdate::Topic => "current_topic",
-            FieldToUpdate::Host => "current_host",
+impl SubscriptionsDialogCreator {
+    fn new(
+        subscriptions: Arc<Mutex<Vec<Subscription>>>,
+        event_sender: UnboundedSender<UIEvent>,
+        done_sender: UnboundedSender<bool>,
+    ) -> SubscriptionsDialogCreator {
+        SubscriptionsDialogCreator {
+            subscriptions,
+            event_sender,
+            done_sender,
+        }
+    }
+
+    /** Consumes self to create view.*/
+    fn create_view(self) -> Dialog {
+        let mut list = SelectView::<usize>::new();
+        if let Ok(subscriptions) = self.subscriptions.lock() {
+            fill_subscriptions(&mut list, &subscriptions);
+        }
+        // We need a clone of self for each submit and button.
+        let on_submit = self.clone();
+        let add = self.clone();
+        let remove = self.clone();
+        let toggle = self.clone();
+        let list = list
+            .on_submit(move |s, idx: &usize| on_submit.toggle(s, *idx))
+            .with_name("subscriptions_list")
+            .scrollable()
+            .max_height(10);
+
+        Dialog::around(list)
+            .title("Subscriptions, ENTER to toggle")
+            .button("ADD", move |s| {
+                s.add_layer(add.clone().create_add_view());
+            })
+            .button("REMOVE", move |s| {
+                if let Some(idx) = selected_subscription(s) {
+                    remove.update(s, |subscriptions| {
+                        subscriptions.remove(idx);
+                    });
+                }
+            })
+            .button("TOGGLE", move |s| {
+                if let Some(idx) = selected_subscription(s) {
+                    toggle.toggle(s, idx);
+                }
+            })
+            .button("CLOSE", |s| {
+                s.pop_layer();
+            })
+    }
+
+    /** Consumes self to create view.*/
+    fn create_add_view(self) -> Dialog {
+        let mut color = SelectView::<String>::new().popup();
+        for name in COLORS {
+            let style = Color::parse(name).unwrap_or(Color::TerminalDefault);
+            color.add_item(StyledString::styled(name, style), name.to_owned());
         }
+
+        let form = ListView::new()
+            .child(
+                "Filter: ",
+                EditView::new()
+                    .with_name("subscription_filter")
+                    .min_width(40),
+            )
+            .child("QoS: ", qos_select().with_name("subscription_qos"))
+            .child("Color: ", color.with_name("subscription_color"));
+
+        Dialog::around(form)
+            .title("New subscription")
+            .button("ADD", move |s| {
+                let subscription = match read_subscription_form(s) {
+                    Ok(subscription) => subscription,
+                    Err(e) => {
+                        s.add_layer(Dialog::info(format!("{}", e)));
+                        return;
+                    }
+                };
+                s.pop_layer();
+                self.update(s, |subscriptions| subscriptions.push(subscription));
+            })
+            .button("CANCEL", |s| {
+                s.pop_layer();
+            })
+    }
+
+    fn toggle(&self, s: &mut Cursive, idx: usize) {
+        self.update(s, |subscriptions| {
+            if let Some(subscription) = subscriptions.get_mut(idx) {
+                subscription.enabled = !subscription.enabled;
+            }
+        });
     }
 
-    fn into_ui_event(&self, val: String) -> UIEvent {
-        match self {
-            FieldToUpdate::Topic => UIEvent::UpdateTopic(val),
-            FieldToUpdate::Host => UIEvent::UpdateHost(val),
+    /// Applies `change`, then saves, resubscribes and redraws the list and the label.
+    fn update(&self, s: &mut Cursive, change: impl FnOnce(&mut Vec<Subscription>)) {
+        let Ok(mut subscriptions) = self.subscriptions.lock() else {
+            return;
+        };
+        change(&mut subscriptions);
+
+        if let Err(e) = self.resubscribe(&subscriptions) {
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
         }
+        s.call_on_name("subscriptions_list", |v: &mut SelectView<usize>| {
+            let selected = v.selected_id();
+            v.clear();
+            fill_subscriptions(v, &subscriptions);
+            if let Some(selected) = selected {
+                v.set_selection(selected.min(subscriptions.len().saturating_sub(1)));
+            }
+        });
+        s.call_on_name("current_subscriptions", |v: &mut TextView| {
+            v.set_content(describe_enabled(&subscriptions));
+        });
+    }
+
+    fn resubscribe(&self, subscriptions: &[Subscription]) -> Result<()> {
+        save_subscriptions(subscriptions)?;
+        self.done_sender.send(true)?;
+        self.event_sender
+            .send(UIEvent::UpdateSubscriptions(subscriptions.to_vec()))?;
+        Ok(())
+    }
+}
+
+/// Items hold the index into the subscriptions, labels are in the subscription's color.
+fn fill_subscriptions(v: &mut SelectView<usize>, subscriptions: &[Subscription]) {
+    v.add_all(subscriptions.iter().enumerate().map(|(idx, subscription)| {
+        let label = StyledString::styled(subscription.describe(), subscription.get_color());
+        (label, idx)
+    }));
+}
+
+fn selected_subscription(s: &mut Cursive) -> Option<usize> {
+    s.call_on_name("subscriptions_list", |v: &mut SelectView<usize>| {
+        v.selection().map(|idx| *idx)
+    })
+    .flatten()
+}
+
+fn read_subscription_form(s: &mut Cursive) -> Result<Subscription> {
+    let filter = s
+        .call_on_name("subscription_filter", |v: &mut EditView| {
+            v.get_content().trim().to_owned()
+        })
+        .unwrap_or_default();
+    validate_filter(&filter)?;
+
+    let mut subscription = Subscription::new(&filter);
+    if let Some(qos) = s
+        .call_on_name("subscription_qos", |v: &mut SelectView<u64>| {
+            v.selection().and_then(|level| qos_from_level(*level))
+        })
+        .flatten()
+    {
+        subscription.qos = qos;
+    }
+    if let Some(color) = s
+        .call_on_name("subscription_color", |v: &mut SelectView<String>| {
+            v.selection().map(|color| color.to_string())
+        })
+        .flatten()
+    {
+        subscription.color = color;
     }
+    Ok(subscription)
 }
 
+/// Dialogs to switch between the broker profiles of the config file, and to save the
+/// current broker and subscriptions as one.
 #[derive(Clone)]
-struct EditFieldDialogCreator {
+struct ProfilesDialogCreator {
+    current_broker: Arc<Mutex<BrokerSettings>>,
+    current_subscriptions: Arc<Mutex<Vec<Subscription>>>,
     event_sender: UnboundedSender<UIEvent>,
     done_sender: UnboundedSender<bool>,
-    field_to_update: FieldToUpdate,
 }
 
-impl EditFieldDialogCreator {
+impl ProfilesDialogCreator {
     fn new(
+        current_broker: Arc<Mutex<BrokerSettings>>,
+        current_subscriptions: Arc<Mutex<Vec<Subscription>>>,
         event_sender: UnboundedSender<UIEvent>,
         done_sender: UnboundedSender<bool>,
-        field_to_update: FieldToUpdate,
-    ) -> EditFieldDialogCreator {
-        EditFieldDialogCreator {
+    ) -> ProfilesDialogCreator {
+        ProfilesDialogCreator {
+            current_broker,
+            current_subscriptions,
             event_sender,
             done_sender,
-            field_to_update,
         }
     }
 
     /** Consumes self to create view.*/
     fn create_view(self) -> Dialog {
-        // We need a clone of sender for each submit and button.
-        let self_clone = self.clone();
-        Dialog::around(EditView::new().on_submit(move |s, val| {
-            // Use 1
-            self_clone.handle_update(s, Some(val));
-        }))
-        .title("New topic")
-        .button("Ok", move |s| {
-            // Use 2
-            self.handle_update(s, None);
-        })
-        .button("Cancel", |s| {
+        let mut list = SelectView::<Profile>::new();
+        match load_profiles() {
+            Ok(profiles) => fill_profiles(&mut list, &profiles),
+            Err(e) => return Dialog::info(format!("Could not read the profiles: {}", e)),
+        }
+        // We need a clone of self for each submit and button.
+        let on_submit = self.clone();
+        let connect = self.clone();
+        let save = self.clone();
+        let list = list
+            .on_submit(move |s, profile: &Profile| on_submit.switch(s, profile))
+            .with_name("profiles_list")
+            .scrollable()
+            .max_height(10);
+
+        Dialog::around(list)
+            .title("Profiles, ENTER to connect")
+            .button("CONNECT", move |s| {
+                if let Some(profile) = selected_profile(s) {
+                    connect.switch(s, &profile);
+                }
+            })
+            .button("SAVE CURRENT", move |s| {
+                s.add_layer(save.clone().create_save_view());
+            })
+            .button("DELETE", |s| {
+                if let Some(profile) = selected_profile(s) {
+                    update_profiles(s, |profiles| {
+                        profiles.retain(|saved| saved.name != profile.name)
+                    });
+                }
+            })
+            .button("CLOSE", |s| {
+                s.pop_layer();
+            })
+    }
+
+    /** Consumes self to create view.*/
+    fn create_save_view(self) -> Dialog {
+        Dialog::around(
+            EditView::new()
+                .on_submit(move |s, name| self.save_current(s, name))
+                .with_name("profile_name")
+                .min_width(30),
+        )
+        .title("Save current broker as, ENTER to save")
+        .button("CANCEL", |s| {
             s.pop_layer();
         })
     }
 
-    fn handle_update(&self, s: &mut Cursive, val: Option<&str>) {
-        if let Some(res) = s.call_on_name(
-            self.field_to_update.into_element_name(),
-            |v: &mut TextView| -> Result<()> {
-                if let Some(val) = val {
-                    v.set_content(val);
-                }
-                self.done_sender.send(true)?;
-                let val = v.get_content().source().to_owned();
-                self.event_sender
-                    .send(self.field_to_update.into_ui_event(val))?;
-                Ok(())
-            },
-        ) {
-            if let Err(e) = res {
-                s.add_layer(Dialog::info(&format!("{:?}", e)));
-            };
+    fn save_current(&self, s: &mut Cursive, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            s.add_layer(Dialog::info("The profile needs a name."));
+            return;
+        }
+        let profile = Profile {
+            name: name.to_owned(),
+            broker: self
+                .current_broker
+                .lock()
+                .map(|broker| broker.clone())
+                .unwrap_or_default(),
+            subscriptions: self
+                .current_subscriptions
+                .lock()
+                .map(|subscriptions| subscriptions.clone())
+                .unwrap_or_default(),
         };
         s.pop_layer();
+        update_profiles(s, |profiles| upsert_profile(profiles, profile));
+    }
+
+    /// Drops the current connection and connects to the broker of `profile`.
+    fn switch(&self, s: &mut Cursive, profile: &Profile) {
+        if let Err(e) = self.done_sender.send(true) {
+            s.add_layer(Dialog::info(format!("{}", e)));
+            return;
+        }
+        if let Err(e) = self
+            .event_sender
+            .send(UIEvent::SwitchProfile(profile.clone()))
+        {
+            s.add_layer(Dialog::info(format!("{}", e)));
+            return;
+        }
+
+        if let Ok(mut current_broker) = self.current_broker.lock() {
+            *current_broker = profile.broker.clone();
+        }
+        s.call_on_name("current_host", |v: &mut TextView| {
+            v.set_content(profile.broker.describe())
+        });
+        if !profile.subscriptions.is_empty()
+            && let Ok(mut current_subscriptions) = self.current_subscriptions.lock()
+        {
+            *current_subscriptions = profile.subscriptions.clone();
+            s.call_on_name("current_subscriptions", |v: &mut TextView| {
+                v.set_content(describe_enabled(&current_subscriptions))
+            });
+        }
+        s.pop_layer();
+    }
+}
+
+fn fill_profiles(v: &mut SelectView<Profile>, profiles: &[Profile]) {
+    v.add_all(
+        profiles
+            .iter()
+            .map(|profile| (profile.describe(), profile.clone())),
+    );
+}
+
+fn selected_profile(s: &mut Cursive) -> Option<Profile> {
+    s.call_on_name("profiles_list", |v: &mut SelectView<Profile>| {
+        v.selection().map(|profile| (*profile).clone())
+    })
+    .flatten()
+}
+
+/// Applies `change` to the saved profiles and redraws the list.
+fn update_profiles(s: &mut Cursive, change: impl FnOnce(&mut Vec<Profile>)) {
+    let result = load_profiles().and_then(|mut profiles| {
+        change(&mut profiles);
+        save_profiles(&profiles)?;
+        Ok(profiles)
+    });
+    match result {
+        Ok(profiles) => {
+            s.call_on_name("profiles_list", |v: &mut SelectView<Profile>| {
+                v.clear();
+                fill_profiles(v, &profiles);
+            });
+        }
+        Err(e) => s.add_layer(Dialog::info(format!("Something went wrong {}", e))),
     }
 }
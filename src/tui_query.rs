@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Button, Dialog, DummyView, EditView, LinearLayout, SelectView, TextView},
+};
+
+use crate::{
+    db_interactions::{DBRow, QueryResult, run_query},
+    table_layout::TableLayout,
+    tui_tables::create_row_detail_dialog,
+    utils::config_dir,
+};
+
+const HISTORY_LIMIT: usize = 200;
+/// Columns of the screen taken by the buttons, the history and the borders.
+const SCREEN_MARGIN: usize = 66;
+
+fn history_path() -> PathBuf {
+    config_dir().join("query_history.txt")
+}
+
+/// Queries are stored one per line, oldest first.
+fn load_history() -> Vec<String> {
+    fs::read_to_string(history_path())
+        .map(|content| {
+            content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_history(history: &[String]) -> Result<()> {
+    fs::create_dir_all(config_dir())?;
+    fs::write(history_path(), history.join("\n"))?;
+    Ok(())
+}
+
+fn add_to_history(history: &mut Vec<String>, query: &str) {
+    // Queries are typed into a single line EditView, but keep the file one query per line.
+    let query = query.replace('\n', " ");
+    history.retain(|previous| *previous != query);
+    history.push(query);
+    if history.len() > HISTORY_LIMIT {
+        history.drain(..history.len() - HISTORY_LIMIT);
+    }
+}
+
+pub fn draw_query(s: &mut Cursive, main_menu_id: usize) {
+    let history = Arc::new(Mutex::new(load_history()));
+    let history_for_submit = history.clone();
+    let history_for_run = history.clone();
+
+    let buttons = LinearLayout::vertical()
+        .child(Button::new("RUN", move |s| {
+            let query = s
+                .call_on_name("query_input", |v: &mut EditView| v.get_content())
+                .unwrap_or_default();
+            handle_run_query(s, &query, history_for_run.clone());
+        }))
+        .child(Button::new("CLEAR", |s| {
+            clear_results(s);
+            s.call_on_name("query_status", |v: &mut TextView| v.set_content(""));
+        }))
+        .child(DummyView)
+        .child(Button::new("MAIN MENU", move |s| {
+            s.set_screen(main_menu_id);
+        }));
+
+    let input = EditView::new()
+        .on_submit(move |s, query| {
+            handle_run_query(s, query, history_for_submit.clone());
+        })
+        .with_name("query_input")
+        .min_width(60);
+
+    let results = LinearLayout::vertical()
+        .child(Dialog::around(input).title("SQL"))
+        .child(TextView::new("").with_name("query_header"))
+        .child(
+            SelectView::<DBRow>::new()
+                .on_submit(|s, row| s.add_layer(create_row_detail_dialog(row)))
+                .with_name("query_results")
+                .scrollable()
+                .min_height(10),
+        )
+        .child(TextView::new("").with_name("query_status"));
+
+    let history_view = SelectView::<String>::new()
+        .on_submit(|s, query: &String| {
+            s.call_on_name("query_input", |v: &mut EditView| {
+                v.set_content(query.to_owned());
+            });
+            if s.focus_name("query_input").is_err() {
+                s.add_layer(Dialog::info("View not found."));
+            }
+        })
+        .with_name("query_history")
+        .scrollable()
+        .max_width(40);
+
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::horizontal()
+                .child(buttons)
+                .child(DummyView)
+                .child(results)
+                .child(DummyView)
+                .child(Dialog::around(history_view).title("History")),
+        )
+        .title("QUERY"),
+    );
+
+    if let Ok(history) = history.lock() {
+        show_history(s, &history);
+    };
+}
+
+fn show_history(s: &mut Cursive, history: &[String]) {
+    s.call_on_name("query_history", |v: &mut SelectView<String>| {
+        v.clear();
+        // Newest first, same as the logs screen.
+        v.add_all(
+            history
+                .iter()
+                .rev()
+                .map(|query| (query.to_owned(), query.to_owned())),
+        );
+    });
+}
+
+fn clear_results(s: &mut Cursive) {
+    s.call_on_name("query_header", |v: &mut TextView| v.set_content(""));
+    s.call_on_name("query_results", |v: &mut SelectView<DBRow>| v.clear());
+}
+
+fn handle_run_query(s: &mut Cursive, query: &str, history: Arc<Mutex<Vec<String>>>) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+
+    match history.lock() {
+        Ok(mut history) => {
+            add_to_history(&mut history, query);
+            if let Err(e) = save_history(&history) {
+                s.add_layer(Dialog::info(format!("Could not save query history: {}", e)));
+            }
+            show_history(s, &history);
+        }
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+        }
+    }
+
+    clear_results(s);
+    let status = match run_query(query) {
+        Ok(QueryResult::Rows {
+            columns,
+            rows,
+            truncated,
+        }) => {
+            let row_count = rows.len();
+            let names: Vec<String> = columns.iter().map(|column| column.name.clone()).collect();
+            let cells: Vec<Vec<String>> = rows.iter().map(|row| row.display_values()).collect();
+            let available = s.screen_size().x.saturating_sub(SCREEN_MARGIN);
+            let layout = TableLayout::fit(&names, &cells, &[], available);
+            s.call_on_name("query_header", |v: &mut TextView| {
+                v.set_content(layout.format(&names));
+            });
+            s.call_on_name("query_results", |v: &mut SelectView<DBRow>| {
+                v.add_all(
+                    rows.into_iter()
+                        .zip(cells)
+                        .map(|(row, cells)| (layout.format(&cells), row)),
+                );
+            });
+            match truncated {
+                true => format!(
+                    "First {} rows, the rest were left out. Add a LIMIT or WHERE to narrow it down.",
+                    row_count
+                ),
+                false => format!("{} rows", row_count),
+            }
+        }
+        Ok(QueryResult::RowsAffected(rows_changed)) => {
+            format!("{} rows affected", rows_changed)
+        }
+        Err(e) => format!("Error: {}", e),
+    };
+
+    s.call_on_name("query_status", |v: &mut TextView| v.set_content(status));
+}
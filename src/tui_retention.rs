@@ -0,0 +1,279 @@
+use std::{
+    io::Error,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, DummyView, EditView, LinearLayout, ListView, SelectView, TextView},
+};
+
+use crate::{
+    db_interactions::DataTable,
+    retention::{
+        ALL_TOPICS, Downsample, RetentionRule, RuleEffect, apply_retention, load_rules,
+        preview_retention, save_rules,
+    },
+    time_range::parse_duration,
+};
+
+/// Dialog listing the retention rules, opened from the CONFIGURE screen.
+pub fn draw_retention_rules(s: &mut Cursive) {
+    let rules = match load_rules() {
+        Ok(rules) => Arc::new(Mutex::new(rules)),
+        Err(e) => {
+            s.add_layer(Dialog::info(format!(
+                "Could not read retention rules: {}",
+                e
+            )));
+            return;
+        }
+    };
+    let rules_for_add = rules.clone();
+    let rules_for_remove = rules.clone();
+    let rules_for_preview = rules.clone();
+    let rules_for_run = rules.clone();
+
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(
+                    SelectView::<RetentionRule>::new()
+                        .with_name("retention_rules")
+                        .scrollable()
+                        .min_size((60, 4)),
+                )
+                .child(DummyView)
+                .child(
+                    TextView::new("PREVIEW shows how many rows each rule removes.")
+                        .with_name("retention_effects")
+                        .scrollable()
+                        .max_height(12),
+                ),
+        )
+        .title("Retention rules")
+        .button("ADD", move |s| {
+            s.add_layer(create_rule_dialog(rules_for_add.clone()));
+        })
+        .button("REMOVE", move |s| {
+            let selected = s
+                .call_on_name("retention_rules", |v: &mut SelectView<RetentionRule>| {
+                    v.selection()
+                })
+                .flatten();
+            let Some(selected) = selected else {
+                s.add_layer(Dialog::info("No rules selected."));
+                return;
+            };
+            update_rules(s, rules_for_remove.clone(), |rules| {
+                rules.retain(|rule| *rule != *selected)
+            });
+        })
+        .button("PREVIEW", move |s| {
+            let res = rules_for_preview
+                .lock()
+                .map_err(|_| std::io::Error::other("Failed to lock mutex.").into())
+                .and_then(|rules| preview_retention(&rules));
+            show_effects(s, res);
+        })
+        .button("RUN", move |s| {
+            handle_run_retention(s, rules_for_run.clone());
+        })
+        .button("CLOSE", |s| {
+            s.pop_layer();
+        }),
+    );
+
+    if let Ok(rules) = rules.lock() {
+        show_rules(s, &rules);
+    }
+}
+
+fn show_rules(s: &mut Cursive, rules: &[RetentionRule]) {
+    s.call_on_name("retention_rules", |v: &mut SelectView<RetentionRule>| {
+        v.clear();
+        v.add_all(rules.iter().map(|rule| (rule.describe(), rule.clone())));
+    });
+}
+
+fn show_effects(s: &mut Cursive, effects: Result<Vec<RuleEffect>>) {
+    let content = match effects {
+        Ok(effects) if effects.is_empty() => "No retention rules configured.".to_owned(),
+        Ok(effects) => effects
+            .iter()
+            .map(|effect| effect.describe())
+            .collect::<Vec<String>>()
+            .join("\n"),
+        Err(e) => format!("Error: {}", e),
+    };
+    s.call_on_name("retention_effects", |v: &mut TextView| {
+        v.set_content(content)
+    });
+}
+
+/// Changes the rules, saves them and shows them again.
+fn update_rules(
+    s: &mut Cursive,
+    rules: Arc<Mutex<Vec<RetentionRule>>>,
+    change: impl FnOnce(&mut Vec<RetentionRule>),
+) {
+    let Ok(mut rules) = rules.lock() else {
+        s.add_layer(Dialog::info("Failed to lock mutex."));
+        return;
+    };
+    change(&mut rules);
+    if let Err(e) = save_rules(&rules) {
+        s.add_layer(Dialog::info(format!(
+            "Could not save retention rules: {}",
+            e
+        )));
+    }
+    show_rules(s, &rules);
+}
+
+/// Shows what the rules would remove and only runs them once confirmed.
+fn handle_run_retention(s: &mut Cursive, rules: Arc<Mutex<Vec<RetentionRule>>>) {
+    let rules = match rules.lock() {
+        Ok(rules) => rules.clone(),
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+    if rules.is_empty() {
+        s.add_layer(Dialog::info("No retention rules configured."));
+        return;
+    }
+    let effects = match preview_retention(&rules) {
+        Ok(effects) => effects,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            return;
+        }
+    };
+    let preview: Vec<String> = effects.iter().map(|effect| effect.describe()).collect();
+
+    s.add_layer(
+        Dialog::around(TextView::new(preview.join("\n")).scrollable())
+            .title("Run retention rules?")
+            .button("RUN", move |s| {
+                s.pop_layer();
+                let res = apply_retention(&rules);
+                show_effects(s, res);
+            })
+            .button("CANCEL", |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn create_rule_dialog(rules: Arc<Mutex<Vec<RetentionRule>>>) -> Dialog {
+    let tables = SelectView::<DataTable>::new()
+        .with_all(
+            DataTable::ALL
+                .iter()
+                .map(|table| (table.get_table_name().to_owned(), *table)),
+        )
+        .with_name("retention_table");
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(tables)
+            .child(DummyView)
+            .child(
+                ListView::new()
+                    .child(
+                        "Topic prefix: ",
+                        EditView::new().with_name("retention_prefix").min_width(20),
+                    )
+                    .child(
+                        "Keep raw rows: ",
+                        EditView::new()
+                            .content("30d")
+                            .with_name("retention_keep_raw"),
+                    )
+                    .child(
+                        "Average over: ",
+                        EditView::new().with_name("retention_bucket"),
+                    )
+                    .child(
+                        "Keep averages: ",
+                        EditView::new().with_name("retention_keep_averages"),
+                    ),
+            )
+            .child(TextView::new(
+                "Durations like 15m, 1h, 30d or 2y. Leave the averages empty to drop old rows.",
+            ))
+            .child(TextView::new("").with_name("retention_rule_error")),
+    )
+    .title("Add retention rule")
+    .button("OK", move |s| {
+        let existing = match rules.lock() {
+            Ok(rules) => rules.clone(),
+            Err(_) => {
+                s.add_layer(Dialog::info("Failed to lock mutex."));
+                return;
+            }
+        };
+        match read_rule_dialog(s, &existing) {
+            Ok(rule) => {
+                s.pop_layer();
+                update_rules(s, rules.clone(), |rules| rules.push(rule));
+            }
+            Err(e) => {
+                s.call_on_name("retention_rule_error", |v: &mut TextView| {
+                    v.set_content(format!("Error: {}", e))
+                });
+            }
+        }
+    })
+    .button("CANCEL", |s| {
+        s.pop_layer();
+    })
+}
+
+/// Reads the rule being added, which can not share its table and prefix with `existing`.
+fn read_rule_dialog(s: &mut Cursive, existing: &[RetentionRule]) -> Result<RetentionRule> {
+    let mut read = |name: &str| {
+        s.call_on_name(name, |v: &mut EditView| v.get_content().trim().to_owned())
+            .unwrap_or_default()
+    };
+    let topic_prefix = match read("retention_prefix") {
+        prefix if prefix == ALL_TOPICS => "".to_owned(),
+        prefix => prefix,
+    };
+    let keep_raw = read("retention_keep_raw");
+    let bucket = read("retention_bucket");
+    let keep_averages = read("retention_keep_averages");
+    let table = s
+        .call_on_name("retention_table", |v: &mut SelectView<DataTable>| {
+            v.selection().map(|table| *table)
+        })
+        .flatten()
+        .unwrap_or(DataTable::Measurements);
+
+    let downsample = match (bucket.is_empty(), keep_averages.is_empty()) {
+        (true, true) => None,
+        _ => Some(Downsample {
+            bucket: parse_duration(&bucket)?,
+            keep: parse_duration(&keep_averages)?,
+        }),
+    };
+    let rule = RetentionRule {
+        table,
+        topic_prefix,
+        keep_raw: parse_duration(&keep_raw)?,
+        downsample,
+    };
+    rule.validate()?;
+    if existing
+        .iter()
+        .any(|other| other.table == rule.table && other.topic_prefix == rule.topic_prefix)
+    {
+        return Err(Error::other("There already is a rule for this table and prefix.").into());
+    }
+
+    Ok(rule)
+}
@@ -1,19 +1,74 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashSet,
+    sync::{Arc, LazyLock, Mutex, Once},
+    thread,
+    time::Duration,
+};
 
-use crate::db_interactions::{DBRow, delete_row_from_table, get_all_from_table, get_tables};
+use crate::{
+    db_interactions::{
+        ChangeWatcher, ColumnKind, DBRow, DataTable, TableColumn, TableFilter, TableSort,
+        UNDO_ROW_LIMIT, count_rows_in_table, count_rows_matching, delete_matching_from_table,
+        delete_rows_from_table, get_page_from_table, get_rows_after, get_table_columns, get_tables,
+        insert_row_into_table, restore_rows_to_table, update_row_in_table,
+    },
+    export::{ExportFormat, export_table},
+    import::{ImportFormat, ImportOptions, preview_import, run_import},
+    row_detail::describe_row,
+    stats::{StatsBucket, TopicStats, get_topic_stats},
+    table_layout::TableLayout,
+    time_range::{SHORTCUTS, TimeRange, parse_time_bound},
+    topic_tree::{TopicNode, get_topic_tree, subtree_filter},
+    tui_chart::draw_chart,
+};
 use anyhow::Result;
+use chrono::Local;
 use cursive::{
-    Cursive,
-    view::{Nameable, Scrollable},
+    Cursive, Printer, Vec2, View,
+    direction::Direction,
+    event::{Event, EventResult, Key, MouseButton, MouseEvent},
+    view::{CannotFocus, Nameable, Resizable, Scrollable},
     views::{
-        Button, Dialog, DummyView, EditView, LinearLayout, NamedView, ScrollView, SelectView,
-        TextView,
+        Button, Dialog, DummyView, EditView, LinearLayout, ListView, NamedView, OnEventView,
+        ScrollView, SelectView, TextView,
     },
 };
 
+/// Rows fetched from the database at a time.
+const PAGE_SIZE: usize = 200;
+/// How close to the last loaded row the selection has to be before the next page is fetched.
+const PAGE_FETCH_MARGIN: usize = 10;
+/// Replaces the leading `|` of the label of rows marked for DELETE MANY.
+const MARKED_PREFIX: &str = "*";
+/// Columns of the screen taken by the buttons, the dialog borders and the scrollbar.
+const SCREEN_MARGIN: usize = 24;
+/// Fits the columns of an open table screen to the terminal again after it is resized.
+static RESIZE_HOOK: Once = Once::new();
+
+/// Seconds between checks for new rows in live mode, until the user picks another interval.
+const DEFAULT_LIVE_INTERVAL: u64 = 2;
+
+/// Live mode of the table screen, which polls for rows written by other processes.
+struct LiveState {
+    enabled: bool,
+    interval: Duration,
+    /// Bumped whenever live mode is toggled or the screen is left, so older threads stop.
+    generation: usize,
+}
+
+/// Rows removed by a delete in this session, restored newest first by UNDO.
+struct DeletedRows {
+    table_name: String,
+    rows: Vec<DBRow>,
+}
+
+static UNDO_BUFFER: LazyLock<Mutex<Vec<DeletedRows>>> = LazyLock::new(|| Mutex::new(vec![]));
+
 pub fn draw_db_explorer(s: &mut Cursive, main_menu_id: usize) {
     s.pop_layer();
-    if let Some(_) = s.call_on_name("tables_list", |_v: &mut Dialog| {}) {
+    if s.call_on_name("tables_list", |_v: &mut Dialog| {})
+        .is_some()
+    {
         s.pop_layer();
     };
 
@@ -41,181 +96,1810 @@ fn draw_table(s: &mut Cursive, table_name: &str, main_menu_id: usize) -> Result<
     s.pop_layer();
 
     let selected_row = Arc::new(Mutex::new(Option::<DBRow>::None));
-    let val_filter = Arc::new(Mutex::new(String::new()));
+    let table_filter = Arc::new(Mutex::new(TableFilter::default()));
+    let live = Arc::new(Mutex::new(LiveState {
+        enabled: false,
+        interval: Duration::from_secs(DEFAULT_LIVE_INTERVAL),
+        generation: 0,
+    }));
 
     let buttons = create_buttons(
         selected_row.clone(),
-        val_filter.clone(),
+        table_filter.clone(),
+        live,
         table_name,
         main_menu_id,
     );
-    let row_container = create_row_container(selected_row);
+    let row_container = create_row_container(selected_row, table_filter.clone(), table_name);
+    let names = get_table_columns(table_name)?
+        .iter()
+        .map(|column| column.name.clone())
+        .collect();
+    let table_filter_for_sort = table_filter.clone();
+    let table_name_for_sort = table_name.to_owned();
+    let header = TableHeader {
+        names,
+        fixed_widths: vec![],
+        layout: TableLayout::default(),
+        sort: None,
+        on_click: Arc::new(move |s, column| {
+            sort_by_column(
+                s,
+                table_filter_for_sort.clone(),
+                &table_name_for_sort,
+                column,
+            );
+        }),
+    }
+    .with_name("table_header");
+    RESIZE_HOOK.call_once(|| s.set_on_pre_event(Event::WindowResize, fit_columns));
 
-    s.add_layer(Dialog::around(
-        LinearLayout::horizontal()
-            .child(buttons)
-            .child(DummyView)
-            .child(row_container),
-    ));
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::horizontal()
+                .child(buttons)
+                .child(DummyView)
+                .child(LinearLayout::vertical().child(header).child(row_container)),
+        )
+        .title(table_name),
+    );
 
-    let val_filter = val_filter.clone();
-    update_table(s, table_name, val_filter)
+    update_table(s, table_name, table_filter)
 }
 
 fn create_row_container(
     selected_row: Arc<Mutex<Option<DBRow>>>,
-) -> ScrollView<NamedView<SelectView<DBRow>>> {
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+) -> ScrollView<OnEventView<NamedView<SelectView<DBRow>>>> {
     let selected_row_clone = selected_row.clone();
     let selected_row_submit_clone = selected_row.clone();
-    SelectView::<DBRow>::new()
+    let table_name = table_name.to_owned();
+    let table = SelectView::<DBRow>::new()
         .on_select(move |s, row| {
             if let Ok(mut selected_row) = selected_row_clone.lock() {
                 *selected_row = Some(row.to_owned());
             } else {
                 s.add_layer(Dialog::info("Failed to lock mutex."));
             }
+            // Fetch the next page once the selection gets close to the last loaded row.
+            let near_end = s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+                v.selected_id()
+                    .is_some_and(|idx| idx + PAGE_FETCH_MARGIN >= v.len())
+            });
+            if near_end == Some(true)
+                && let Err(e) = load_next_page(s, &table_name, table_filter.clone())
+            {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
         })
         .on_submit(move |s, row| {
-            if let Err(_) = s.focus_name("db_helper_button") {
-                s.add_layer(Dialog::info("View not found."));
-            }
             if let Ok(mut selected_row) = selected_row_submit_clone.lock() {
                 *selected_row = Some(row.to_owned());
             }
+            s.add_layer(create_row_detail_dialog(row));
         })
-        .with_name("main_table")
+        .with_name("main_table");
+
+    OnEventView::new(table)
+        .on_event(' ', toggle_mark)
         .scrollable()
 }
 
+type ColumnCallback = Arc<dyn Fn(&mut Cursive, usize) + Send + Sync>;
+
+/// Column names above the rows of the table screen, lined up with them. Clicking a name
+/// sorts the table by that column.
+struct TableHeader {
+    names: Vec<String>,
+    /// Widths set in the COLUMNS dialog, `None` fits the column to its values.
+    fixed_widths: Vec<Option<usize>>,
+    layout: TableLayout,
+    sort: Option<TableSort>,
+    on_click: ColumnCallback,
+}
+
+impl TableHeader {
+    /// Names with an arrow after the one the table is sorted by.
+    fn cells(&self) -> Vec<String> {
+        self.names
+            .iter()
+            .map(|name| match &self.sort {
+                Some(sort) if sort.column == *name => {
+                    format!("{} {}", name, if sort.descending { "▼" } else { "▲" })
+                }
+                _ => name.to_owned(),
+            })
+            .collect()
+    }
+}
+
+impl View for TableHeader {
+    fn draw(&self, printer: &Printer) {
+        printer.print((0, 0), &self.layout.format(&self.cells()));
+    }
+
+    fn required_size(&mut self, _constraint: Vec2) -> Vec2 {
+        Vec2::new(self.layout.row_width(), 1)
+    }
+
+    // Only clicks focus the header, the keyboard goes past it to the rows.
+    fn take_focus(&mut self, source: Direction) -> Result<EventResult, CannotFocus> {
+        match source == Direction::none() {
+            true => Ok(EventResult::Consumed(None)),
+            false => Err(CannotFocus),
+        }
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        let Event::Mouse {
+            offset,
+            position,
+            event: MouseEvent::Press(MouseButton::Left),
+        } = event
+        else {
+            return EventResult::Ignored;
+        };
+        let column = position
+            .checked_sub(offset)
+            .and_then(|position| self.layout.column_at(position.x));
+        match column {
+            Some(column) => {
+                let on_click = self.on_click.clone();
+                EventResult::with_cb(move |s| on_click(s, column))
+            }
+            None => EventResult::Ignored,
+        }
+    }
+}
+
+fn current_layout(s: &mut Cursive) -> TableLayout {
+    s.call_on_name("table_header", |v: &mut TableHeader| v.layout.clone())
+        .unwrap_or_default()
+}
+
+/// Fits the column widths to the loaded rows and the terminal, and labels the rows again.
+fn fit_columns(s: &mut Cursive) {
+    let cells = s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+        v.iter()
+            .map(|(_, row)| row.display_values())
+            .collect::<Vec<Vec<String>>>()
+    });
+    let Some(cells) = cells else {
+        return;
+    };
+    let available = s.screen_size().x.saturating_sub(SCREEN_MARGIN);
+    let layout = s.call_on_name("table_header", |v: &mut TableHeader| {
+        v.layout = TableLayout::fit(&v.cells(), &cells, &v.fixed_widths, available);
+        v.layout.clone()
+    });
+    let Some(layout) = layout else {
+        return;
+    };
+
+    s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+        v.iter_mut().for_each(|(label, row)| {
+            let marked = label.source().starts_with(MARKED_PREFIX);
+            *label = row_label(row, marked, &layout).into();
+        });
+    });
+}
+
+/// Dialog to sort by a column and to set column widths from the keyboard.
+fn handle_columns(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let header = s.call_on_name("table_header", |v: &mut TableHeader| {
+        (v.names.clone(), v.fixed_widths.clone(), v.layout.clone())
+    });
+    let Some((names, fixed_widths, layout)) = header else {
+        s.add_layer(Dialog::info("View not found."));
+        return;
+    };
+    let current_sort = table_filter
+        .lock()
+        .map(|table_filter| table_filter.sort.clone())
+        .unwrap_or_default();
+
+    let mut sorts = SelectView::<Option<TableSort>>::new().item("insertion order", None);
+    names.iter().for_each(|name| {
+        [false, true].into_iter().for_each(|descending| {
+            let direction = if descending {
+                "descending"
+            } else {
+                "ascending"
+            };
+            sorts.add_item(
+                format!("{} {}", name, direction),
+                Some(TableSort {
+                    column: name.to_owned(),
+                    descending,
+                }),
+            );
+        });
+    });
+    let selected = sorts
+        .iter()
+        .position(|(_, sort)| *sort == current_sort)
+        .unwrap_or_default();
+    sorts.set_selection(selected);
+
+    let mut widths = ListView::new();
+    names.iter().enumerate().for_each(|(idx, name)| {
+        let content = match fixed_widths.get(idx).copied().flatten() {
+            Some(width) => width.to_string(),
+            None => "".to_owned(),
+        };
+        widths.add_child(
+            format!("{}: ", name),
+            EditView::new()
+                .content(content)
+                .with_name(format!("column_width_{}", idx))
+                .min_width(6),
+        );
+    });
+
+    let table_name = table_name.to_owned();
+    let column_count = names.len();
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(Dialog::around(sorts.with_name("column_sort").scrollable()).title("Sort by"))
+                .child(Dialog::around(widths.scrollable()).title("Widths"))
+                .child(TextView::new(format!(
+                    "Leave a width empty to fit the column. Now: {}",
+                    layout
+                        .widths
+                        .iter()
+                        .map(|width| width.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))),
+        )
+        .title("Columns")
+        .button("OK", move |s| {
+            let fixed_widths = (0..column_count)
+                .map(|idx| {
+                    let width = s
+                        .call_on_name(&format!("column_width_{}", idx), |v: &mut EditView| {
+                            v.get_content().trim().to_owned()
+                        })
+                        .unwrap_or_default();
+                    match width.is_empty() {
+                        true => Ok(None),
+                        false => width
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|width| *width > 0)
+                            .map(Some)
+                            .ok_or(width),
+                    }
+                })
+                .collect::<Result<Vec<Option<usize>>, String>>();
+            let fixed_widths = match fixed_widths {
+                Ok(fixed_widths) => fixed_widths,
+                Err(width) => {
+                    s.add_layer(Dialog::info(format!("{} is not a column width.", width)));
+                    return;
+                }
+            };
+            let sort = s
+                .call_on_name("column_sort", |v: &mut SelectView<Option<TableSort>>| {
+                    v.selection()
+                })
+                .flatten()
+                .and_then(|sort| (*sort).clone());
+
+            match table_filter.lock() {
+                Ok(mut table_filter) => table_filter.sort = sort,
+                Err(_) => {
+                    s.add_layer(Dialog::info("Failed to lock mutex."));
+                    return;
+                }
+            }
+            s.call_on_name("table_header", |v: &mut TableHeader| {
+                v.fixed_widths = fixed_widths
+            });
+            s.pop_layer();
+            if let Err(e) = update_table(s, &table_name, table_filter.clone()) {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
+        })
+        .button("CANCEL", |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+/// Sorts by `column` ascending, then descending, then goes back to insertion order.
+fn sort_by_column(
+    s: &mut Cursive,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+    column: usize,
+) {
+    let name = s
+        .call_on_name("table_header", |v: &mut TableHeader| {
+            v.names.get(column).cloned()
+        })
+        .flatten();
+    let Some(name) = name else {
+        return;
+    };
+    match table_filter.lock() {
+        Ok(mut table_filter) => {
+            table_filter.sort = match table_filter.sort.take() {
+                Some(sort) if sort.column == name && !sort.descending => Some(TableSort {
+                    column: name,
+                    descending: true,
+                }),
+                Some(sort) if sort.column == name => None,
+                _ => Some(TableSort {
+                    column: name,
+                    descending: false,
+                }),
+            };
+        }
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    }
+    if let Err(e) = update_table(s, table_name, table_filter) {
+        s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+    }
+}
+
+/// Every value of a row in full, opened with ENTER on the row.
+pub(crate) fn create_row_detail_dialog(row: &DBRow) -> Dialog {
+    let sections =
+        describe_row(row)
+            .into_iter()
+            .fold(LinearLayout::vertical(), |sections, section| {
+                sections.child(Dialog::around(TextView::new(section.body)).title(section.title))
+            });
+
+    Dialog::around(sections.scrollable().max_height(40).min_width(60))
+        .title("Row")
+        .button("CLOSE", |s| {
+            s.pop_layer();
+        })
+}
+
+fn row_label(row: &DBRow, marked: bool, layout: &TableLayout) -> String {
+    let label = layout.format_row(row);
+    match marked {
+        true => format!("{}{}", MARKED_PREFIX, &label[1..]),
+        false => label,
+    }
+}
+
+/// Marks or unmarks the selected row for DELETE MANY. The mark only lives in the label, so
+/// reloading the table clears it.
+fn toggle_mark(s: &mut Cursive) {
+    let layout = current_layout(s);
+    s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+        if let Some(idx) = v.selected_id()
+            && let Some((label, row)) = v.get_item_mut(idx)
+        {
+            let marked = label.source().starts_with(MARKED_PREFIX);
+            *label = row_label(row, !marked, &layout).into();
+        }
+    });
+}
+
+fn get_marked_rows(s: &mut Cursive) -> Vec<DBRow> {
+    s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+        v.iter()
+            .filter(|(label, _)| label.starts_with(MARKED_PREFIX))
+            .map(|(_, row)| row.clone())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
 fn create_buttons(
     selected_row: Arc<Mutex<Option<DBRow>>>,
-    val_filter: Arc<Mutex<String>>,
+    table_filter: Arc<Mutex<TableFilter>>,
+    live: Arc<Mutex<LiveState>>,
     table_name: &str,
     main_menu_id: usize,
 ) -> LinearLayout {
     let table_name = Arc::new(table_name.to_owned());
     let table_name_cp = table_name.clone();
     let table_name_cp_cp = table_name.clone();
-    let val_filter_for_filter = val_filter.clone();
+    let table_name_for_export = table_name.clone();
+    let table_filter_for_filter = table_filter.clone();
+    let table_filter_for_export = table_filter.clone();
+    let table_name_for_import = table_name.clone();
+    let table_filter_for_import = table_filter.clone();
+    let selected_row_for_edit = selected_row.clone();
+    let table_filter_for_edit = table_filter.clone();
+    let table_name_for_edit = table_name.clone();
+    let table_filter_for_insert = table_filter.clone();
+    let table_name_for_insert = table_name.clone();
+    let table_filter_for_bulk_delete = table_filter.clone();
+    let table_name_for_bulk_delete = table_name.clone();
+    let table_filter_for_undo = table_filter.clone();
+    let table_name_for_undo = table_name.clone();
+    let table_filter_for_stats = table_filter.clone();
+    let table_name_for_stats = table_name.clone();
+    let selected_row_for_chart = selected_row.clone();
+    let table_name_for_chart = table_name.clone();
+    let table_filter_for_topics = table_filter.clone();
+    let table_name_for_topics = table_name.clone();
+    let table_filter_for_columns = table_filter.clone();
+    let table_name_for_columns = table_name.clone();
+    let table_filter_for_live = table_filter.clone();
+    let table_name_for_live = table_name.clone();
+    let live_for_live = live.clone();
+    let live_for_change_table = live.clone();
 
     LinearLayout::vertical()
         .child(Button::new("FILTER", move |s| {
-            handle_filter_db_rows(s, val_filter_for_filter.clone(), &table_name_cp);
+            handle_filter_db_rows(s, table_filter_for_filter.clone(), &table_name_cp);
+        }))
+        .child(
+            Button::new("LIVE", move |s| {
+                handle_live_mode(
+                    s,
+                    live_for_live.clone(),
+                    table_filter_for_live.clone(),
+                    &table_name_for_live,
+                );
+            })
+            .with_name("live_button"),
+        )
+        .child(Button::new("COLUMNS", move |s| {
+            handle_columns(s, table_filter_for_columns.clone(), &table_name_for_columns);
+        }))
+        .child(Button::new("TOPICS", move |s| {
+            handle_topic_tree(s, table_filter_for_topics.clone(), &table_name_for_topics);
+        }))
+        .child(Button::new("STATS", move |s| {
+            s.add_layer(create_stats_dialog(
+                table_filter_for_stats.clone(),
+                &table_name_for_stats,
+            ));
+            refresh_stats(s, table_filter_for_stats.clone(), &table_name_for_stats);
+        }))
+        .child(Button::new("CHART", move |s| {
+            handle_chart_topic(s, selected_row_for_chart.clone(), &table_name_for_chart);
+        }))
+        .child(Button::new("EXPORT", move |s| {
+            s.add_layer(create_export_dialog(
+                table_filter_for_export.clone(),
+                &table_name_for_export,
+            ));
+        }))
+        .child(Button::new(
+            "IMPORT",
+            move |s| match DataTable::from_table_name(&table_name_for_import) {
+                Some(table) => {
+                    s.add_layer(create_import_dialog(table, table_filter_for_import.clone()));
+                }
+                None => {
+                    s.add_layer(Dialog::info(
+                        "Only MEASUREMENTS and LOGS can be imported into.",
+                    ));
+                }
+            },
+        ))
+        .child(Button::new("EDIT", move |s| {
+            handle_edit_db_row(
+                s,
+                selected_row_for_edit.clone(),
+                table_filter_for_edit.clone(),
+                &table_name_for_edit,
+            );
+        }))
+        .child(Button::new("INSERT", move |s| {
+            match get_table_columns(&table_name_for_insert) {
+                Ok(columns) => {
+                    s.add_layer(create_row_dialog(
+                        Arc::new(columns),
+                        None,
+                        table_filter_for_insert.clone(),
+                        &table_name_for_insert,
+                    ));
+                }
+                Err(e) => {
+                    s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+                }
+            }
         }))
         .child(
             Button::new("DELETE", move |s| {
-                // Passed a reference of val_filter to handle delete, becuase table needs to be updated.
+                // Passed a reference of table_filter to handle delete, becuase table needs to be updated.
                 handle_delete_db_row(
                     s,
                     selected_row.clone(),
-                    val_filter.clone(),
+                    table_filter.clone(),
                     &table_name_cp_cp,
                 );
             })
             .with_name("db_helper_button"),
         )
+        .child(Button::new("DELETE MANY", move |s| {
+            handle_delete_many(
+                s,
+                table_filter_for_bulk_delete.clone(),
+                &table_name_for_bulk_delete,
+            );
+        }))
+        .child(Button::new("UNDO", move |s| {
+            handle_undo_delete(s, table_filter_for_undo.clone(), &table_name_for_undo);
+        }))
         .child(DummyView)
         .child(Button::new("CHANGE TABLE", move |s| {
+            stop_live_mode(&live_for_change_table);
             s.pop_layer();
             draw_db_explorer(s, main_menu_id);
         }))
         .child(Button::new("MAIN MENU", move |s| {
+            stop_live_mode(&live);
             s.set_screen(main_menu_id);
         }))
 }
 
-fn handle_filter_db_rows(s: &mut Cursive, val_filter: Arc<Mutex<String>>, table_name: &str) {
-    let filter_dialog = create_filter_dialog(val_filter, table_name);
+/// Stops live mode, or asks for the interval and starts it.
+fn handle_live_mode(
+    s: &mut Cursive,
+    live: Arc<Mutex<LiveState>>,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+) {
+    let (enabled, interval) = match live.lock() {
+        Ok(live) => (live.enabled, live.interval),
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+    if enabled {
+        stop_live_mode(&live);
+        set_live_label(s, false);
+        return;
+    }
+
+    let table_name = table_name.to_owned();
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(
+                    ListView::new().child(
+                        "Check every (seconds): ",
+                        EditView::new()
+                            .content(interval.as_secs().to_string())
+                            .with_name("live_interval")
+                            .min_width(6),
+                    ),
+                )
+                .child(TextView::new(
+                    "New rows are added on top while the table screen is open.",
+                )),
+        )
+        .title("Live mode")
+        .button("START", move |s| {
+            let interval = s
+                .call_on_name("live_interval", |v: &mut EditView| {
+                    v.get_content().trim().parse::<u64>()
+                })
+                .and_then(|interval| interval.ok())
+                .filter(|interval| *interval > 0);
+            let Some(interval) = interval else {
+                s.add_layer(Dialog::info(
+                    "The interval has to be a whole number of seconds.",
+                ));
+                return;
+            };
+            s.pop_layer();
+            start_live_mode(
+                s,
+                live.clone(),
+                table_filter.clone(),
+                &table_name,
+                Duration::from_secs(interval),
+            );
+        })
+        .button("CANCEL", |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn start_live_mode(
+    s: &mut Cursive,
+    live: Arc<Mutex<LiveState>>,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+    interval: Duration,
+) {
+    // Opened here so a table without rowids or a missing database is reported right away.
+    let watcher = table_filter
+        .lock()
+        .map_err(|_| std::io::Error::other("Failed to lock mutex.").into())
+        .and_then(|filter| get_rows_after(table_name, &filter, i64::MAX, 1))
+        .and_then(|_| ChangeWatcher::open());
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Can not start live mode: {}", e)));
+            return;
+        }
+    };
+    let generation = match live.lock() {
+        Ok(mut live) => {
+            live.enabled = true;
+            live.interval = interval;
+            live.generation += 1;
+            live.generation
+        }
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+    set_live_label(s, true);
+    // Rows written while live mode was off.
+    if let Err(e) = add_new_rows(s, table_name, table_filter.clone()) {
+        s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+    }
+
+    let is_current = move |live: &Arc<Mutex<LiveState>>| {
+        live.lock()
+            .is_ok_and(|live| live.enabled && live.generation == generation)
+    };
+    let table_name = table_name.to_owned();
+    let screen = s.active_screen();
+    let sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(interval);
+            if !is_current(&live) {
+                break;
+            }
+            // Errors of a single check, like a locked database, are retried on the next one.
+            let changed = watcher.has_changed().unwrap_or(false);
+            let live = live.clone();
+            let table_name = table_name.clone();
+            let table_filter = table_filter.clone();
+            let sent = sink.send(Box::new(move |s| {
+                if !is_current(&live) {
+                    return;
+                }
+                // Leaving the screen, also with the global `b` key, ends live mode.
+                let active = s.active_screen();
+                if active != screen {
+                    stop_live_mode(&live);
+                    s.set_screen(screen);
+                    set_live_label(s, false);
+                    s.set_screen(active);
+                    return;
+                }
+                if changed && let Err(e) = add_new_rows(s, &table_name, table_filter) {
+                    stop_live_mode(&live);
+                    set_live_label(s, false);
+                    s.add_layer(Dialog::info(format!("Live mode stopped: {}", e)));
+                }
+            }));
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+fn stop_live_mode(live: &Arc<Mutex<LiveState>>) {
+    if let Ok(mut live) = live.lock() {
+        live.enabled = false;
+        live.generation += 1;
+    }
+}
+
+fn set_live_label(s: &mut Cursive, enabled: bool) {
+    s.call_on_name("live_button", |v: &mut Button| {
+        v.set_label(if enabled { "LIVE (ON)" } else { "LIVE" })
+    });
+}
+
+/// Adds the rows inserted after the newest row of the table on top, keeping the selected row
+/// selected. Reloads the table when too many rows came in to add them one by one.
+fn add_new_rows(
+    s: &mut Cursive,
+    table_name: &str,
+    table_filter: Arc<Mutex<TableFilter>>,
+) -> Result<()> {
+    let newest = s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+        v.iter().filter_map(|(_, row)| row.rowid).max()
+    });
+    let Some(newest) = newest else {
+        return Ok(());
+    };
+    let Some(newest) = newest else {
+        return update_table(s, table_name, table_filter);
+    };
+
+    let rows = match table_filter.lock() {
+        // New rows of a sorted table can belong anywhere, not just on top.
+        Ok(filter) if filter.sort.is_some() => None,
+        Ok(filter) => Some(get_rows_after(table_name, &filter, newest, PAGE_SIZE + 1)?),
+        Err(_) => return Err(std::io::Error::other("Failed to lock mutex.").into()),
+    };
+    let Some(rows) = rows.filter(|rows| rows.len() <= PAGE_SIZE) else {
+        return update_table(s, table_name, table_filter);
+    };
+
+    let layout = current_layout(s);
+    s.call_on_name("main_table", |v: &mut SelectView<DBRow>| {
+        let selected = v.selected_id();
+        let added = rows.len();
+        rows.into_iter()
+            .enumerate()
+            .for_each(|(idx, row)| v.insert_item(idx, row_label(&row, false, &layout), row));
+        if let Some(selected) = selected {
+            // The callback would only report the same row as selected again.
+            let _ = v.set_selection(selected + added);
+        }
+    });
+    Ok(())
+}
+
+fn handle_filter_db_rows(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let filter_dialog = create_filter_dialog(table_filter, table_name);
     s.add_layer(filter_dialog);
 }
 
-fn create_filter_dialog(val_filter: Arc<Mutex<String>>, table_name: &str) -> Dialog {
-    let val_filter_cp = val_filter.clone();
-    let val_filter_cp_cp = val_filter.clone();
+fn create_filter_dialog(table_filter: Arc<Mutex<TableFilter>>, table_name: &str) -> Dialog {
+    let table_name = Arc::new(table_name.to_owned());
+    let table_name_cp = table_name.clone();
+    let table_filter_cp = table_filter.clone();
+    let current_filter = table_filter
+        .lock()
+        .map(|table_filter| table_filter.clone())
+        .unwrap_or_default();
+
+    let shortcuts = SelectView::<String>::new()
+        .with_all_str(SHORTCUTS)
+        .on_submit(|s, shortcut: &String| {
+            match TimeRange::parse_shortcut(shortcut, Local::now()) {
+                Ok(time_range) => {
+                    s.call_on_name("filter_from", |v: &mut EditView| {
+                        v.set_content(TimeRange::format_bound(time_range.from));
+                    });
+                    s.call_on_name("filter_to", |v: &mut EditView| {
+                        v.set_content(TimeRange::format_bound(time_range.to));
+                    });
+                }
+                Err(e) => {
+                    s.add_layer(Dialog::info(format!("{}", e)));
+                }
+            }
+        });
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(
+                ListView::new()
+                    .child(
+                        "Value: ",
+                        EditView::new()
+                            .content(current_filter.text)
+                            .on_submit(move |s, _| {
+                                apply_filter_dialog(s, table_filter_cp.clone(), &table_name_cp);
+                            })
+                            .with_name("filter_text")
+                            .min_width(30),
+                    )
+                    .child(
+                        "From:  ",
+                        EditView::new()
+                            .content(TimeRange::format_bound(current_filter.time_range.from))
+                            .with_name("filter_from"),
+                    )
+                    .child(
+                        "To:    ",
+                        EditView::new()
+                            .content(TimeRange::format_bound(current_filter.time_range.to))
+                            .with_name("filter_to"),
+                    )
+                    .child(
+                        "Topic: ",
+                        EditView::new()
+                            .content(current_filter.topic)
+                            .with_name("filter_topic"),
+                    ),
+            )
+            .child(TextView::new(
+                "Times are local, e.g. 2025-06-01 14:00, 15m ago or now.\n\
+                 Topics take MQTT wildcards, e.g. /home/+/temp or /home/#.",
+            ))
+            .child(DummyView)
+            .child(Dialog::around(shortcuts).title("Shortcuts")),
+    )
+    .title("Filter")
+    .button("OK", move |s| {
+        apply_filter_dialog(s, table_filter.clone(), &table_name);
+    })
+    .button("CLEAR", |s| {
+        ["filter_text", "filter_from", "filter_to", "filter_topic"]
+            .iter()
+            .for_each(|name| {
+                s.call_on_name(name, |v: &mut EditView| {
+                    v.set_content("");
+                });
+            });
+    })
+    .button("CANCEL", |s| {
+        s.pop_layer();
+    })
+}
+
+/// Reads the fields of the filter dialog into `table_filter` and reloads the table.
+fn apply_filter_dialog(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let mut read_field = |name: &str| {
+        s.call_on_name(name, |v: &mut EditView| v.get_content().to_string())
+            .unwrap_or_default()
+    };
+    let text = read_field("filter_text");
+    let from = read_field("filter_from");
+    let to = read_field("filter_to");
+    let topic = read_field("filter_topic").trim().to_owned();
+
+    let now = Local::now();
+    let time_range = parse_time_bound(&from, now).and_then(|from| {
+        Ok(TimeRange {
+            from,
+            to: parse_time_bound(&to, now)?,
+        })
+    });
+
+    let time_range = match time_range {
+        Ok(time_range) => time_range,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("{}", e)));
+            return;
+        }
+    };
+
+    match table_filter.lock() {
+        Ok(mut table_filter) => {
+            table_filter.text = text;
+            table_filter.time_range = time_range;
+            table_filter.topic = topic;
+        }
+        Err(_) => {
+            s.add_layer(Dialog::info("Something went wrong on submission."));
+            return;
+        }
+    }
+
+    if let Err(e) = update_table(s, table_name, table_filter) {
+        s.add_layer(Dialog::info(format!(
+            "Something went wrong on submission: {}",
+            e
+        )));
+    } else {
+        s.pop_layer();
+    };
+}
+
+/// Exports the rows matching the current filter and time range.
+fn create_export_dialog(table_filter: Arc<Mutex<TableFilter>>, table_name: &str) -> Dialog {
     let table_name = table_name.to_owned();
+    let file_stem = format!("./{}", table_name.to_lowercase());
+    let default_path =
+        move |format: &ExportFormat| format!("{}.{}", file_stem, format.get_extension());
+    let initial_path = default_path(&ExportFormat::Csv);
+
+    let formats = SelectView::<ExportFormat>::new()
+        .with_all(
+            ExportFormat::ALL
+                .iter()
+                .map(|format| (format.get_title().to_owned(), *format)),
+        )
+        .on_select(move |s, format| {
+            s.call_on_name("export_path", |v: &mut EditView| {
+                v.set_content(default_path(format));
+            });
+        })
+        .with_name("export_format");
+
     Dialog::around(
-        EditView::new()
-            .on_edit(move |s, val, _| {
-                if let Ok(mut val_filter) = val_filter_cp.lock() {
-                    *val_filter = val.to_owned();
-                } else {
-                    s.add_layer(Dialog::info("Something went wrong on edit."));
-                };
-            })
-            .on_submit(move |s, val| {
-                if let Ok(mut val_filter) = val_filter_cp_cp.lock() {
-                    *val_filter = val.to_owned();
-                } else {
-                    s.add_layer(Dialog::info("Something went wrong on submission."));
-                };
-            }),
+        LinearLayout::vertical()
+            .child(formats)
+            .child(DummyView)
+            .child(
+                ListView::new().child(
+                    "File: ",
+                    EditView::new()
+                        .content(initial_path)
+                        .with_name("export_path")
+                        .min_width(30),
+                ),
+            ),
     )
-    .title("Enter Value Filter: ")
+    .title("Export")
     .button("OK", move |s| {
-        let val_filter = val_filter.clone(); // For updating table
-        if let Err(e) = update_table(s, &table_name, val_filter) {
-            s.add_layer(Dialog::info(format!(
-                "Something went wrong on submission: {}",
-                e
-            )));
-        } else {
+        let format = s
+            .call_on_name("export_format", |v: &mut SelectView<ExportFormat>| {
+                v.selection().map(|format| *format)
+            })
+            .flatten()
+            .unwrap_or(ExportFormat::Csv);
+        let path = s
+            .call_on_name("export_path", |v: &mut EditView| {
+                v.get_content().to_string()
+            })
+            .unwrap_or_default();
+
+        let table_filter = match table_filter.lock() {
+            Ok(table_filter) => table_filter.clone(),
+            Err(_) => {
+                s.add_layer(Dialog::info("Failed to lock mutex."));
+                return;
+            }
+        };
+
+        s.pop_layer();
+        match export_table(&table_name, &table_filter, format, &path) {
+            Ok(row_count) => {
+                s.add_layer(Dialog::info(format!(
+                    "{} rows exported to {}",
+                    row_count, path
+                )));
+            }
+            Err(e) => {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
+        }
+    })
+    .button("CANCEL", |s| {
+        s.pop_layer();
+    })
+}
+
+/// Topics of the table screen, with the levels the user opened.
+struct TopicTreeState {
+    root: TopicNode,
+    expanded: HashSet<String>,
+}
+
+/// Shows the topics of the table as a tree. Submitting a level filters the table to it.
+fn handle_topic_tree(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let root = match get_topic_tree(table_name) {
+        Ok(root) => root,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            return;
+        }
+    };
+    let state = Arc::new(Mutex::new(TopicTreeState {
+        root,
+        expanded: HashSet::new(),
+    }));
+    let state_for_toggle = state.clone();
+    let state_for_expand = state.clone();
+    let state_for_collapse = state.clone();
+    let table_name = Arc::new(table_name.to_owned());
+    let table_name_for_submit = table_name.clone();
+    let table_name_for_filter = table_name.clone();
+    let table_filter_for_submit = table_filter.clone();
+    let table_filter_for_filter = table_filter.clone();
+
+    let tree = SelectView::<String>::new()
+        .on_submit(move |s, path: &String| {
+            filter_topic(
+                s,
+                table_filter_for_submit.clone(),
+                &table_name_for_submit,
+                subtree_filter(path),
+            );
+        })
+        .with_name("topic_tree");
+    let tree = OnEventView::new(tree)
+        .on_event(' ', move |s| {
+            expand_topic(s, state_for_toggle.clone(), None);
+        })
+        .on_event(Key::Right, move |s| {
+            expand_topic(s, state_for_expand.clone(), Some(true));
+        })
+        .on_event(Key::Left, move |s| {
+            expand_topic(s, state_for_collapse.clone(), Some(false));
+        });
+
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new(
+                    "SPACE opens or closes a level, ENTER filters the table to it.",
+                ))
+                .child(tree.scrollable().min_size((60, 10))),
+        )
+        .title(format!("Topics in {}", table_name))
+        .button("FILTER", move |s| {
+            let selected = s
+                .call_on_name("topic_tree", |v: &mut SelectView<String>| v.selection())
+                .flatten();
+            if let Some(path) = selected {
+                filter_topic(
+                    s,
+                    table_filter_for_filter.clone(),
+                    &table_name_for_filter,
+                    subtree_filter(&path),
+                );
+            }
+        })
+        .button("ALL TOPICS", move |s| {
+            filter_topic(s, table_filter.clone(), &table_name, "".to_owned());
+        })
+        .button("CLOSE", |s| {
             s.pop_layer();
+        }),
+    );
+
+    show_topic_tree(s, state);
+}
+
+fn show_topic_tree(s: &mut Cursive, state: Arc<Mutex<TopicTreeState>>) {
+    let Ok(state) = state.lock() else {
+        s.add_layer(Dialog::info("Failed to lock mutex."));
+        return;
+    };
+    let items: Vec<(String, String)> = state
+        .root
+        .visible(&state.expanded)
+        .iter()
+        .map(|(depth, node)| {
+            let marker = match (
+                node.children.is_empty(),
+                state.expanded.contains(&node.path),
+            ) {
+                (true, _) => " ",
+                (false, true) => "▾",
+                (false, false) => "▸",
+            };
+            let name = if node.name.is_empty() {
+                "/"
+            } else {
+                &node.name
+            };
+            let last_seen = match node.last_seen {
+                Some(_) => format!(", last {}", TimeRange::format_bound(node.last_seen)),
+                None => "".to_owned(),
+            };
+            let label = format!(
+                "{}{} {} ({} rows{})",
+                "  ".repeat(*depth),
+                marker,
+                name,
+                node.count,
+                last_seen
+            );
+            (label, node.path.clone())
+        })
+        .collect();
+
+    s.call_on_name("topic_tree", |v: &mut SelectView<String>| {
+        let selected = v.selected_id().unwrap_or_default();
+        v.clear();
+        v.add_all(items);
+        v.set_selection(selected);
+    });
+}
+
+/// Opens or closes the selected level, or toggles it when `open` is `None`.
+fn expand_topic(s: &mut Cursive, state: Arc<Mutex<TopicTreeState>>, open: Option<bool>) {
+    let selected = s
+        .call_on_name("topic_tree", |v: &mut SelectView<String>| v.selection())
+        .flatten();
+    let Some(path) = selected else {
+        return;
+    };
+    if let Ok(mut state) = state.lock() {
+        let open = open.unwrap_or(!state.expanded.contains(path.as_str()));
+        match open {
+            true => state.expanded.insert(path.to_string()),
+            false => state.expanded.remove(path.as_str()),
         };
+    }
+    show_topic_tree(s, state);
+}
+
+/// Sets the topic filter of the table screen and closes the topic tree.
+fn filter_topic(
+    s: &mut Cursive,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+    topic: String,
+) {
+    match table_filter.lock() {
+        Ok(mut table_filter) => table_filter.topic = topic,
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    }
+    s.pop_layer();
+    if let Err(e) = update_table(s, table_name, table_filter) {
+        s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+    }
+}
+
+/// Charts the topic of the selected row.
+fn handle_chart_topic(s: &mut Cursive, selected_row: Arc<Mutex<Option<DBRow>>>, table_name: &str) {
+    let topic = match selected_row.lock() {
+        Ok(selected_row) => selected_row
+            .as_ref()
+            .and_then(|row| row.get("topic").cloned()),
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+
+    match topic {
+        Some(ColumnKind::STRING(topic)) => draw_chart(s, table_name, &topic),
+        Some(_) => s.add_layer(Dialog::info("The selected row has no topic.")),
+        None => s.add_layer(Dialog::info("Select a row of the topic to chart.")),
+    }
+}
+
+/// Most topic and bucket groups shown by the stats dialog.
+const STATS_LIMIT: usize = 1000;
+
+fn create_stats_dialog(table_filter: Arc<Mutex<TableFilter>>, table_name: &str) -> Dialog {
+    let table_name = table_name.to_owned();
+    let table_name_cp = table_name.clone();
+    let table_filter_cp = table_filter.clone();
+    let buckets = SelectView::<StatsBucket>::new()
+        .popup()
+        .with_all(
+            StatsBucket::ALL
+                .iter()
+                .map(|bucket| (bucket.get_title().to_owned(), *bucket)),
+        )
+        .selected(1)
+        .on_submit(move |s, _: &StatsBucket| {
+            refresh_stats(s, table_filter.clone(), &table_name);
+        })
+        .with_name("stats_bucket");
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Bucket: "))
+                    .child(buckets),
+            )
+            .child(TextView::new("").with_name("stats_status"))
+            .child(DummyView)
+            .child(TextView::new(TopicStats::format_header()))
+            .child(
+                TextView::new("")
+                    .with_name("stats_rows")
+                    .scrollable()
+                    .max_height(20),
+            ),
+    )
+    .title(format!("Stats for {}", table_name_cp))
+    .button("REFRESH", move |s| {
+        refresh_stats(s, table_filter_cp.clone(), &table_name_cp);
+    })
+    .button("CLOSE", |s| {
+        s.pop_layer();
+    })
+}
+
+/// Recomputes the stats dialog for the selected bucket and the current filter.
+fn refresh_stats(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let bucket = s
+        .call_on_name("stats_bucket", |v: &mut SelectView<StatsBucket>| {
+            v.selection().map(|bucket| *bucket)
+        })
+        .flatten()
+        .unwrap_or(StatsBucket::Hour);
+    let filter = table_filter
+        .lock()
+        .map(|table_filter| table_filter.clone())
+        .unwrap_or_default();
+
+    let (status, rows) = match get_topic_stats(table_name, &filter, bucket, STATS_LIMIT) {
+        Ok(stats) => {
+            let rows: Vec<String> = stats.iter().map(String::from).collect();
+            let status = match filter.is_empty() {
+                true => format!("{} groups of numeric values.", stats.len()),
+                false => format!(
+                    "{} groups of numeric values matching the filter.",
+                    stats.len()
+                ),
+            };
+            (status, rows.join("\n"))
+        }
+        Err(e) => (format!("Error: {}", e), "".to_owned()),
+    };
+
+    s.call_on_name("stats_status", |v: &mut TextView| v.set_content(status));
+    s.call_on_name("stats_rows", |v: &mut TextView| v.set_content(rows));
+}
+
+/// Rows parsed by the PREVIEW button of the import dialog.
+const IMPORT_PREVIEW_ROWS: usize = 10;
+
+fn create_import_dialog(table: DataTable, table_filter: Arc<Mutex<TableFilter>>) -> Dialog {
+    let mut mapping = ListView::new();
+    DataTable::COLUMN_NAMES.iter().for_each(|column| {
+        mapping.add_child(
+            format!("{} <- ", column),
+            EditView::new()
+                .content(*column)
+                .with_name(format!("import_map_{}", column)),
+        );
+    });
+
+    let formats = SelectView::<ImportFormat>::new()
+        .with_all(
+            ImportFormat::ALL
+                .iter()
+                .map(|format| (format.get_title().to_owned(), *format)),
+        )
+        .with_name("import_format");
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(
+                ListView::new().child(
+                    "File: ",
+                    EditView::new()
+                        .on_edit(|s, path, _| {
+                            if let Some(format) = ImportFormat::from_path(path) {
+                                s.call_on_name(
+                                    "import_format",
+                                    |v: &mut SelectView<ImportFormat>| {
+                                        let idx = ImportFormat::ALL
+                                            .iter()
+                                            .position(|candidate| *candidate == format);
+                                        v.set_selection(idx.unwrap_or(0));
+                                    },
+                                );
+                            }
+                        })
+                        .with_name("import_path")
+                        .min_width(30),
+                ),
+            )
+            .child(formats)
+            .child(DummyView)
+            .child(Dialog::around(mapping).title("Column <- field"))
+            .child(
+                TextView::new("")
+                    .with_name("import_preview")
+                    .scrollable()
+                    .max_height(12),
+            ),
+    )
+    .title(format!("Import into {}", table.get_table_name()))
+    .button("PREVIEW", move |s| {
+        let options = read_import_dialog(s, table);
+        let available = s.screen_size().x.saturating_sub(SCREEN_MARGIN);
+        let preview = preview_import(&options, IMPORT_PREVIEW_ROWS)
+            .map(|summary| {
+                let names: Vec<String> = table
+                    .get_columns()
+                    .iter()
+                    .map(|column| column.name.clone())
+                    .collect();
+                let cells: Vec<Vec<String>> = summary
+                    .preview
+                    .iter()
+                    .map(|row| row.display_values())
+                    .collect();
+                let layout = TableLayout::fit(&names, &cells, &[], available);
+                let rows: Vec<String> = cells.iter().map(|cells| layout.format(cells)).collect();
+                let errors = summary.errors.join("\n");
+                format!("{}\n{}\n{}", layout.format(&names), rows.join("\n"), errors)
+            })
+            .unwrap_or_else(|e| format!("Error: {}", e));
+        s.call_on_name("import_preview", |v: &mut TextView| v.set_content(preview));
+    })
+    .button("IMPORT", move |s| {
+        let options = read_import_dialog(s, table);
+        match run_import(&options) {
+            Ok(summary) => {
+                s.pop_layer();
+                s.add_layer(Dialog::info(summary.describe()));
+                if let Err(e) = update_table(s, table.get_table_name(), table_filter.clone()) {
+                    s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+                }
+            }
+            Err(e) => {
+                s.call_on_name("import_preview", |v: &mut TextView| {
+                    v.set_content(format!("Error: {}", e))
+                });
+            }
+        }
     })
     .button("CANCEL", |s| {
         s.pop_layer();
     })
 }
 
-fn handle_delete_db_row(
+fn read_import_dialog(s: &mut Cursive, table: DataTable) -> ImportOptions {
+    let path = s
+        .call_on_name("import_path", |v: &mut EditView| {
+            v.get_content().to_string()
+        })
+        .unwrap_or_default();
+    let format = s
+        .call_on_name("import_format", |v: &mut SelectView<ImportFormat>| {
+            v.selection().map(|format| *format)
+        })
+        .flatten()
+        .unwrap_or(ImportFormat::Csv);
+    let mapping = DataTable::COLUMN_NAMES
+        .iter()
+        .filter_map(|column| {
+            let field = s.call_on_name(&format!("import_map_{}", column), |v: &mut EditView| {
+                v.get_content().to_string()
+            })?;
+            Some((column.to_string(), field))
+        })
+        .collect();
+
+    ImportOptions {
+        path,
+        format,
+        table,
+        mapping,
+    }
+}
+
+fn handle_edit_db_row(
     s: &mut Cursive,
     selected_row: Arc<Mutex<Option<DBRow>>>,
-    val_filter: Arc<Mutex<String>>,
+    table_filter: Arc<Mutex<TableFilter>>,
     table_name: &str,
 ) {
     match selected_row.lock() {
-        Ok(mut selected_row) => {
-            let inspected = selected_row.clone().inspect(|row| {
-                match delete_row_from_table(row, &table_name) {
-                    Ok(rows_changed) => {
-                        s.add_layer(Dialog::info(format!(
-                            "{} rows deleted: {}",
-                            rows_changed, row
-                        )));
-                        // Filter is referenced here to update the table. This needs to read the filter to know what to render.
-                        if let Err(e) = update_table(s, table_name, val_filter) {
-                            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
-                        };
-                        *selected_row = None;
-                    }
-                    Err(e) => {
-                        s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
-                    }
-                };
-            });
-            if let None = inspected {
+        Ok(selected_row) => match selected_row.clone() {
+            Some(row) => {
+                let columns = row.columns.clone();
+                s.add_layer(create_row_dialog(
+                    columns,
+                    Some(row),
+                    table_filter,
+                    table_name,
+                ));
+            }
+            None => {
                 s.add_layer(Dialog::info("No rows selected."));
             }
+        },
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+        }
+    }
+}
+
+/// Form with a field per column. Edits `original` when given, otherwise inserts a new row.
+fn create_row_dialog(
+    columns: Arc<Vec<TableColumn>>,
+    original: Option<DBRow>,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+) -> Dialog {
+    let table_name = table_name.to_owned();
+    let initial_values: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| match &original {
+            Some(row) => row.values[idx].to_raw_string(),
+            None if column.name == "timestamp" => Local::now().timestamp().to_string(),
+            None => "".to_owned(),
+        })
+        .collect();
+
+    let mut fields = ListView::new();
+    columns.iter().enumerate().for_each(|(idx, column)| {
+        fields.add_child(
+            format!("{} ({}): ", column.name, column.decl_type),
+            EditView::new()
+                .content(initial_values[idx].clone())
+                .with_name(format!("row_field_{}", idx))
+                .min_width(30),
+        );
+    });
+
+    let title = match &original {
+        Some(_) => format!("Edit row in {}", table_name),
+        None => format!("Insert row into {}", table_name),
+    };
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(fields)
+            .child(TextView::new("").with_name("row_error")),
+    )
+    .title(title)
+    .button("SAVE", move |s| {
+        let values =
+            match read_row_dialog(s, &table_name, &columns, &initial_values, original.as_ref()) {
+                Ok(values) => values,
+                Err(e) => {
+                    s.call_on_name("row_error", |v: &mut TextView| {
+                        v.set_content(format!("Error: {}", e))
+                    });
+                    return;
+                }
+            };
+        let Some(row) = original.clone() else {
+            save_row(
+                s,
+                None,
+                &columns,
+                &values,
+                &table_name,
+                table_filter.clone(),
+            );
+            return;
+        };
+        // Rows without a rowid are matched on their values and can hit duplicates.
+        match count_rows_matching(&row, &table_name) {
+            Ok(1) => save_row(
+                s,
+                Some(&row),
+                &columns,
+                &values,
+                &table_name,
+                table_filter.clone(),
+            ),
+            Ok(0) => {
+                s.add_layer(Dialog::info("The row no longer exists."));
+            }
+            Ok(count) => {
+                let columns = columns.clone();
+                let table_name = table_name.clone();
+                let table_filter = table_filter.clone();
+                s.add_layer(
+                    Dialog::around(TextView::new(format!(
+                        "{} rows will be updated:\n{}",
+                        count, row
+                    )))
+                    .title("Confirm update")
+                    .button("UPDATE", move |s| {
+                        s.pop_layer();
+                        save_row(
+                            s,
+                            Some(&row),
+                            &columns,
+                            &values,
+                            &table_name,
+                            table_filter.clone(),
+                        );
+                    })
+                    .button("CANCEL", |s| {
+                        s.pop_layer();
+                    }),
+                );
+            }
+            Err(e) => {
+                s.call_on_name("row_error", |v: &mut TextView| {
+                    v.set_content(format!("Error: {}", e))
+                });
+            }
+        }
+    })
+    .button("CANCEL", |s| {
+        s.pop_layer();
+    })
+}
+
+/// Writes the values of the row dialog and closes it, or shows the error in the dialog.
+fn save_row(
+    s: &mut Cursive,
+    original: Option<&DBRow>,
+    columns: &[TableColumn],
+    values: &[ColumnKind],
+    table_name: &str,
+    table_filter: Arc<Mutex<TableFilter>>,
+) {
+    let res = match original {
+        Some(row) => update_row_in_table(row, values, table_name)
+            .map(|rows_changed| format!("{} rows updated.", rows_changed)),
+        None => insert_row_into_table(columns, values, table_name)
+            .map(|rows_changed| format!("{} rows inserted.", rows_changed)),
+    };
+    match res {
+        Ok(message) => {
+            s.pop_layer();
+            s.add_layer(Dialog::info(message));
+            if let Err(e) = update_table(s, table_name, table_filter) {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
+        }
+        Err(e) => {
+            s.call_on_name("row_error", |v: &mut TextView| {
+                v.set_content(format!("Error: {}", e))
+            });
+        }
+    }
+}
+
+/// Reads and validates the fields of the row dialog. Fields that were not changed keep the
+/// original value, so blobs and full float precision survive an edit of another column.
+fn read_row_dialog(
+    s: &mut Cursive,
+    table_name: &str,
+    columns: &[TableColumn],
+    initial_values: &[String],
+    original: Option<&DBRow>,
+) -> Result<Vec<ColumnKind>> {
+    // MEASUREMENTS and LOGS get the stricter checks the importer uses.
+    let data_table = DataTable::from_table_name(table_name);
+
+    columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            let raw = s
+                .call_on_name(&format!("row_field_{}", idx), |v: &mut EditView| {
+                    v.get_content().to_string()
+                })
+                .unwrap_or_default();
+            if let Some(row) = original
+                && raw == initial_values[idx]
+            {
+                return Ok(row.values[idx].clone());
+            }
+            match data_table {
+                Some(data_table) => data_table.parse_value(&column.name, &raw),
+                None => column.parse_value(&raw),
+            }
+        })
+        .collect()
+}
+
+fn handle_delete_db_row(
+    s: &mut Cursive,
+    selected_row: Arc<Mutex<Option<DBRow>>>,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+) {
+    let row = match selected_row.lock() {
+        Ok(selected_row) => selected_row.clone(),
+        Err(_) => {
+            s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+    let Some(row) = row else {
+        s.add_layer(Dialog::info("No rows selected."));
+        return;
+    };
+
+    // Shown before deleting, rows without a rowid are matched on their values.
+    let count = match count_rows_matching(&row, table_name) {
+        Ok(count) => count,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            return;
         }
+    };
+
+    let table_name = table_name.to_owned();
+    s.add_layer(
+        Dialog::around(TextView::new(format!(
+            "{} rows will be deleted:\n{}",
+            count, row
+        )))
+        .title("Confirm delete")
+        .button("DELETE", move |s| {
+            s.pop_layer();
+            match delete_rows_from_table(std::slice::from_ref(&row), &table_name) {
+                Ok(deleted) => {
+                    s.add_layer(Dialog::info(format!(
+                        "{} rows deleted: {}",
+                        deleted.len(),
+                        row
+                    )));
+                    push_undo(&table_name, deleted);
+                    // Filter is referenced here to update the table. This needs to read the filter to know what to render.
+                    if let Err(e) = update_table(s, &table_name, table_filter.clone()) {
+                        s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+                    };
+                    if let Ok(mut selected_row) = selected_row.lock() {
+                        *selected_row = None;
+                    }
+                }
+                Err(e) => {
+                    s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+                }
+            };
+        })
+        .button("CANCEL", |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn handle_delete_many(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let filter = match table_filter.lock() {
+        Ok(table_filter) => table_filter.clone(),
         Err(_) => {
             s.add_layer(Dialog::info("Failed to lock mutex."));
+            return;
+        }
+    };
+    let matching = match count_rows_in_table(table_name, &filter) {
+        Ok(count) => count,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            return;
+        }
+    };
+    let marked = get_marked_rows(s);
+    let filter_note = match filter.is_empty() {
+        true => " No filter is set, this is every row.",
+        false => "",
+    };
+    let undo_note = match matching > UNDO_ROW_LIMIT {
+        true => format!(
+            "\nDeleting more than {} matching rows can not be undone.",
+            UNDO_ROW_LIMIT
+        ),
+        false => "".to_owned(),
+    };
+
+    let table_name = table_name.to_owned();
+    let table_name_cp = table_name.clone();
+    let table_filter_cp = table_filter.clone();
+    s.add_layer(
+        Dialog::around(TextView::new(format!(
+            "{} rows marked with SPACE.\n{} rows match the current filter.{}{}",
+            marked.len(),
+            matching,
+            filter_note,
+            undo_note
+        )))
+        .title("Delete many")
+        .button("DELETE MARKED", move |s| {
+            if marked.is_empty() {
+                s.add_layer(Dialog::info("No rows marked."));
+                return;
+            }
+            s.pop_layer();
+            let res = delete_rows_from_table(&marked, &table_name)
+                .map(|deleted| (deleted.len(), Some(deleted)));
+            finish_delete_many(s, res, table_filter.clone(), &table_name);
+        })
+        .button("DELETE MATCHING", move |s| {
+            s.pop_layer();
+            let res = delete_matching_from_table(&table_name_cp, &filter);
+            finish_delete_many(s, res, table_filter_cp.clone(), &table_name_cp);
+        })
+        .button("CANCEL", |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn finish_delete_many(
+    s: &mut Cursive,
+    res: Result<(usize, Option<Vec<DBRow>>)>,
+    table_filter: Arc<Mutex<TableFilter>>,
+    table_name: &str,
+) {
+    match res {
+        Ok((count, Some(deleted))) => {
+            s.add_layer(Dialog::info(format!(
+                "{} rows deleted. UNDO restores them.",
+                count
+            )));
+            push_undo(table_name, deleted);
+            if let Err(e) = update_table(s, table_name, table_filter) {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
+        }
+        Ok((count, None)) => {
+            s.add_layer(Dialog::info(format!(
+                "{} rows deleted, too many for UNDO to restore.",
+                count
+            )));
+            if let Err(e) = update_table(s, table_name, table_filter) {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
+        }
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+        }
+    }
+}
+
+fn push_undo(table_name: &str, rows: Vec<DBRow>) {
+    if rows.is_empty() {
+        return;
+    }
+    if let Ok(mut undo_buffer) = UNDO_BUFFER.lock() {
+        undo_buffer.push(DeletedRows {
+            table_name: table_name.to_owned(),
+            rows,
+        });
+    }
+}
+
+/// Restores the rows of the last delete in `table_name`.
+fn handle_undo_delete(s: &mut Cursive, table_filter: Arc<Mutex<TableFilter>>, table_name: &str) {
+    let Ok(mut undo_buffer) = UNDO_BUFFER.lock() else {
+        s.add_layer(Dialog::info("Failed to lock mutex."));
+        return;
+    };
+    let Some(idx) = undo_buffer
+        .iter()
+        .rposition(|deleted| deleted.table_name == table_name)
+    else {
+        s.add_layer(Dialog::info("Nothing to undo."));
+        return;
+    };
+
+    let deleted = undo_buffer.remove(idx);
+    match restore_rows_to_table(&deleted.rows, table_name) {
+        Ok(rows_changed) => {
+            drop(undo_buffer);
+            s.add_layer(Dialog::info(format!("{} rows restored.", rows_changed)));
+            if let Err(e) = update_table(s, table_name, table_filter) {
+                s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
+            }
+        }
+        Err(e) => {
+            // Kept so the undo can be retried.
+            undo_buffer.insert(idx, deleted);
+            s.add_layer(Dialog::info(format!("Something went wrong {}", e)));
         }
     }
 }
 
-fn update_table(s: &mut Cursive, table_name: &str, val_filter: Arc<Mutex<String>>) -> Result<()> {
+/// Clears the table, loads the first page of rows matching the filter and fits the columns
+/// to it.
+fn update_table(
+    s: &mut Cursive,
+    table_name: &str,
+    table_filter: Arc<Mutex<TableFilter>>,
+) -> Result<()> {
+    let res = s.call_on_name("main_table", |v: &mut SelectView<DBRow>| v.clear());
+
+    if res.is_none() {
+        s.add_layer(Dialog::info("Something went wrong."));
+        return Ok(());
+    }
+
+    let sort = table_filter
+        .lock()
+        .map(|table_filter| table_filter.sort.clone())
+        .unwrap_or_default();
+    s.call_on_name("table_header", |v: &mut TableHeader| v.sort = sort);
+
+    load_next_page(s, table_name, table_filter)?;
+    fit_columns(s);
+    Ok(())
+}
+
+/// Appends the next page of rows after the ones already in the table.
+fn load_next_page(
+    s: &mut Cursive,
+    table_name: &str,
+    table_filter: Arc<Mutex<TableFilter>>,
+) -> Result<()> {
+    let layout = current_layout(s);
     let res = s.call_on_name("main_table", |v: &mut SelectView<DBRow>| -> Result<()> {
-        if let Ok(val_filter) = val_filter.lock() {
-            let rows = get_all_from_table(table_name)?;
-            v.clear();
+        if let Ok(table_filter) = table_filter.lock() {
+            let last = v
+                .len()
+                .checked_sub(1)
+                .and_then(|idx| v.get_item(idx))
+                .map(|(_, row)| row.clone());
+            let rows =
+                get_page_from_table(table_name, &table_filter, last.as_ref(), v.len(), PAGE_SIZE)?;
             v.add_all(
-                rows.iter()
-                    .rev()
-                    .filter(|row|
-                        row.value.contains(&val_filter.to_string()) ||
-                        row.topic.contains(&val_filter.to_string())
-                    )
-                    .map(|row| (row, row.to_owned())),
+                rows.into_iter()
+                    .map(|row| (row_label(&row, false, &layout), row)),
             );
         };
         Ok(())
@@ -1,7 +1,8 @@
 use std::{
+    env,
     fs::{self, Permissions},
     os::unix::fs::PermissionsExt,
-    path::Path,
+    path::{Path, PathBuf},
 };
 use systemdzbus::{Connection, manager::ManagerProxy};
 
@@ -312,6 +313,19 @@ WantedBy=multi-user.target
     }
 }
 
+/// Directory for files the TUI keeps between sessions. Follows `$XDG_CONFIG_HOME`, falls back
+/// to `~/.config/mqttui`, and finally to the working directory.
+pub fn config_dir() -> PathBuf {
+    let base = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| Path::new(&home).join(".config")));
+
+    match base {
+        Ok(base) => base.join("mqttui"),
+        Err(_) => PathBuf::from("."),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::process;